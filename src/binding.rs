@@ -0,0 +1,311 @@
+use std::{fmt, sync::Arc};
+
+use log::{debug, warn};
+use parking_lot::Mutex;
+
+use crate::{
+    callable::MainCallable,
+    converters::BindingMode,
+    logging::BINDING,
+    markup_extension::{quote_argument, MarkupExtension},
+    properties::PropertyValue,
+    ui_elements::{collect_subtree, UIElementRef, UITree},
+};
+
+// '{Binding Path=..., Mode=..., FallbackValue=...}' on an element property,
+// the path names a property of the data context
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    // empty binds to the data context itself
+    pub path: String,
+    pub mode: BindingMode,
+    // the value of the target while the source has none
+    pub fallback: Option<String>,
+}
+
+impl Binding {
+    pub fn new(path: &str, mode: BindingMode) -> Binding {
+        Binding {
+            path: path.to_string(),
+            mode,
+            fallback: None,
+        }
+    }
+
+    pub fn from_extension(ext: &MarkupExtension) -> Result<Binding, String> {
+        if ext.name != "Binding" {
+            return Err(format!("{{{}}} is not a binding", ext.name));
+        }
+        if ext.positional.len() > 1 {
+            return Err("a binding has a single path".to_string());
+        }
+        let mut binding = Binding::new(ext.positional.first().map(|p| p.as_str()).unwrap_or(""), BindingMode::OneWay);
+        for (name, value) in ext.named.iter() {
+            match name.as_str() {
+                "Path" if !ext.positional.is_empty() => return Err("the path of the binding is given twice".to_string()),
+                "Path" => binding.path = value.to_string(),
+                "Mode" => binding.mode = BindingMode::parse(value)?,
+                "FallbackValue" => binding.fallback = Some(value.to_string()),
+                _ => return Err(format!("unsupported binding parameter '{}'", name)),
+            }
+        }
+        Ok(binding)
+    }
+}
+
+// the markup it was read from, the path positionally and OneWay left out
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut args = Vec::new();
+        if !self.path.is_empty() {
+            args.push(quote_argument(&self.path));
+        }
+        if self.mode != BindingMode::OneWay {
+            args.push(format!("Mode={}", self.mode));
+        }
+        if let Some(v) = &self.fallback {
+            args.push(format!("FallbackValue={}", quote_argument(v)));
+        }
+        if args.is_empty() {
+            return f.write_str("{Binding}");
+        }
+        write!(f, "{{Binding {}}}", args.join(", "))
+    }
+}
+
+struct BoundProperty {
+    element: UIElementRef,
+    property: String,
+    binding: Binding,
+    // the source value last seen or written, compared in its XAML spelling
+    last: Option<String>,
+}
+
+// moves values between the bound element properties and the data context.
+// Writes to the data context are queued by property changed handlers and
+// applied in 'sync', handlers run with the element locked and the code-behind
+// may be locked further up the stack
+pub struct BindingEngine {
+    context: Option<MainCallable>,
+    bound: Vec<BoundProperty>,
+    // (index into 'bound', new target value) of TwoWay bindings
+    pending: Arc<Mutex<Vec<(usize, PropertyValue)>>>,
+}
+
+impl BindingEngine {
+    pub fn new() -> BindingEngine {
+        BindingEngine {
+            context: None,
+            bound: Vec::new(),
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.bound.len()
+    }
+
+    // collects the bindings of 'tree', gives the targets their source values
+    // and starts watching the TwoWay targets
+    pub fn attach(&mut self, tree: &UITree, context: MainCallable) {
+        self.context = Some(context);
+        self.bound.clear();
+        self.pending.lock().clear();
+        let Some(root) = &tree.root else {
+            return;
+        };
+
+        for elt in collect_subtree(root) {
+            let bindings = elt.lock().get_markup().bindings;
+            for (property, binding) in bindings {
+                self.bound.push(BoundProperty {
+                    element: elt.clone(),
+                    property,
+                    binding,
+                    last: None,
+                });
+            }
+        }
+
+        for i in 0..self.bound.len() {
+            self.refresh(i);
+            let b = &self.bound[i];
+            if b.binding.mode != BindingMode::TwoWay {
+                continue;
+            }
+            let pending = self.pending.clone();
+            let property = b.property.to_string();
+            b.element.lock().add_property_changed_handler(Box::new(move |name, value| {
+                if name == property {
+                    pending.lock().push((i, value.clone()));
+                }
+            }));
+        }
+        debug!(target: BINDING, "{} bindings attached", self.bound.len());
+    }
+
+    // writes changed TwoWay targets to the data context, then updates the
+    // OneWay and TwoWay targets whose source changed
+    pub fn sync(&mut self) {
+        let Some(context) = self.context else {
+            return;
+        };
+
+        let pending: Vec<(usize, PropertyValue)> = self.pending.lock().drain(..).collect();
+        for (i, value) in pending {
+            let b = &mut self.bound[i];
+            let text = value.to_string();
+            if b.last.as_deref() == Some(text.as_str()) {
+                continue;
+            }
+            debug!(target: BINDING, "{} <- {} = {}", b.binding.path, b.property, text);
+            if context.lock().set_property(&b.binding.path, value) {
+                b.last = Some(text);
+            } else {
+                warn!(target: BINDING, "the data context has no writable property '{}'", b.binding.path);
+            }
+        }
+
+        for i in 0..self.bound.len() {
+            if self.bound[i].binding.mode != BindingMode::OneTime {
+                self.refresh(i);
+            }
+        }
+    }
+
+    // copies the source value of binding 'i' to its target when it changed
+    fn refresh(&mut self, i: usize) {
+        let Some(context) = self.context else {
+            return;
+        };
+        let b = &mut self.bound[i];
+        let value = context.lock().get_property(&b.binding.path);
+        let text = match value {
+            Some(v) => v.to_string(),
+            None => {
+                if b.last.is_none() {
+                    debug!(target: BINDING, "the data context has no property '{}'", b.binding.path);
+                }
+                return;
+            }
+        };
+        if b.last.as_deref() == Some(text.as_str()) {
+            return;
+        }
+
+        debug!(target: BINDING, "{} -> {} = {}", b.binding.path, b.property, text);
+        if let Err(reason) = b.element.lock().set_value_str(&b.property, &text) {
+            warn!(target: BINDING, "can't set {} from '{}': {}", b.property, b.binding.path, reason);
+        }
+        b.last = Some(text);
+    }
+}
+
+// activates the bindings of the tree of 'mw' with the code-behind as their
+// data context
+pub fn activate(mw: MainCallable) {
+    let tree = mw.lock().get_tree();
+    if let Some(tree) = tree {
+        tree.bindings.lock().attach(tree, mw);
+    }
+}
+
+// brings the bindings of the tree of 'mw' up to date, after events and
+// whenever the code-behind changed its properties
+pub fn sync(mw: MainCallable) {
+    let tree = mw.lock().get_tree();
+    if let Some(tree) = tree {
+        tree.bindings.lock().sync();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use parking_lot::Mutex;
+
+    use crate::binding::{activate, sync, Binding};
+    use crate::callable::CallableByName;
+    use crate::converters::BindingMode;
+    use crate::events::RoutedEventArgs;
+    use crate::markup_extension::MarkupExtension;
+    use crate::properties::PropertyValue;
+    use crate::ui_elements::{UIElementRef, UITreeRef};
+    use crate::xaml_reader;
+
+    struct Model {
+        tree: Option<UITreeRef>,
+        values: HashMap<String, PropertyValue>,
+    }
+
+    impl CallableByName for Model {
+        fn call_method(&mut self, _name: &str, _sender: &UIElementRef, _args: &RoutedEventArgs) {}
+        fn get_tree(&self) -> Option<UITreeRef> {
+            self.tree
+        }
+        fn set_tree(&mut self, tree: Option<UITreeRef>) {
+            self.tree = tree;
+        }
+        fn get_property(&self, name: &str) -> Option<PropertyValue> {
+            self.values.get(name).cloned()
+        }
+        fn set_property(&mut self, name: &str, value: PropertyValue) -> bool {
+            self.values.insert(name.to_string(), value).is_some()
+        }
+    }
+
+    #[test]
+    fn bindings_follow_their_mode() {
+        let ext = MarkupExtension::parse("{Binding Path=Name, Mode=TwoWay}").unwrap();
+        let b = Binding::from_extension(&ext).unwrap();
+        assert_eq!((b.path.as_str(), b.mode), ("Name", BindingMode::TwoWay));
+        assert_eq!(b.to_string(), "{Binding Name, Mode=TwoWay}");
+        let ext = MarkupExtension::parse("{Binding Name, Path=Other}").unwrap();
+        assert!(Binding::from_extension(&ext).is_err());
+        assert!(xaml_reader::read_xaml_str("inline", "<Window Title=\"{StaticResource T}\"/>").is_err());
+
+        let text = "<Window Title=\"{Binding Title, Mode=OneTime}\"><StackPanel>\
+                    <Label Name=\"L\" Text=\"{Binding Name}\"/>\
+                    <TextBlock Name=\"T\" Text=\"{Binding Name, Mode=TwoWay}\" Width=\"{Binding Size, FallbackValue=10}\"/>\
+                    </StackPanel></Window>";
+        let tree = Box::leak(Box::new(xaml_reader::read_xaml_str("inline", text).unwrap()));
+        let mut values = HashMap::new();
+        values.insert("Title".to_string(), PropertyValue::Str("First".to_string()));
+        values.insert("Name".to_string(), PropertyValue::Str("Ada".to_string()));
+        let model = Box::leak(Box::new(Mutex::new(Model { tree: Some(tree), values })));
+
+        let text_of = |name: &str, property: &str| {
+            let elt = tree.find_by_name(name).unwrap();
+            let v = elt.lock().get_value(property).unwrap().to_string();
+            v
+        };
+        let root = tree.root.clone().unwrap();
+        activate(model);
+        assert_eq!(tree.bindings.lock().len(), 4);
+        assert_eq!(root.lock().get_value("Title").unwrap().to_string(), "First");
+        assert_eq!(text_of("L", "Text"), "Ada");
+        assert_eq!(text_of("T", "Text"), "Ada");
+        assert_eq!(text_of("T", "Width"), "10");
+
+        // OneWay and TwoWay targets follow the source, OneTime does not
+        model.lock().set_property("Title", PropertyValue::Str("Second".to_string()));
+        model.lock().set_property("Name", PropertyValue::Str("Grace".to_string()));
+        sync(model);
+        assert_eq!(root.lock().get_value("Title").unwrap().to_string(), "First");
+        assert_eq!(text_of("L", "Text"), "Grace");
+        assert_eq!(text_of("T", "Text"), "Grace");
+
+        // a TwoWay target writes back, and the OneWay target follows
+        let tb = tree.find_by_name("T").unwrap();
+        tb.lock().set_value("Text", PropertyValue::Str("Edsger".to_string()));
+        sync(model);
+        assert_eq!(model.lock().get_property("Name"), Some(PropertyValue::Str("Edsger".to_string())));
+        assert_eq!(text_of("L", "Text"), "Edsger");
+
+        model.lock().values.insert("Size".to_string(), PropertyValue::Int(42));
+        sync(model);
+        assert_eq!(tb.lock().get_value("Width"), Some(PropertyValue::Float(42.0)));
+    }
+}
@@ -1,51 +1,95 @@
 use std::collections::HashMap;
 
-use crate::{events::Event, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef}, visitor::Visitor};
+use crate::{properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue}, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup}, visitor::Visitor};
 
 
 pub struct Button {
     common: UICommon,
 }
 
+static BUTTON_PROPERTIES: [PropertyDef; 3] = [
+    PropertyDef::unset("Content", PropertyKind::Str),
+    PropertyDef::unset("Click", PropertyKind::Str),
+    PropertyDef::unset("FontSize", PropertyKind::Float),
+];
+
 impl UIAlloc for Button {
     fn new(attributes: &HashMap<String, String>, id: String) -> Button {
         Button {
-            common: UICommon::new(attributes, "Button", id),
+            common: UICommon::new(attributes, "Button", id, &BUTTON_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &BUTTON_PROPERTIES
+    }
 }
 
 impl Button {
     pub fn get_text(&self) -> String { 
-        self.common.get_attr("Content")
+        self.common.get_str("Content")
+    }
+
+    pub fn get_font_size(&self) -> Option<f64> {
+        self.common.get_f64("FontSize")
     }
 }
 
 impl UIElement for Button {
 
     fn add_content_string(&mut self, s: String) {
-        self.common.set_attr("Content", s);
+        self.common.set_content("Content", &s);
     }
 
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
 
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        println!("NOTICE: button-handle-event");
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     
     fn get_ui_type_name(&self) -> &'static str {
@@ -56,6 +100,11 @@ impl UIElement for Button {
         self.common.add_child(child, parent);
     }
     
+
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
+
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {} ", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
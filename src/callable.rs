@@ -1,12 +1,123 @@
 use parking_lot::Mutex;
 
-use crate::ui_elements::UITreeRef;
+use crate::{
+    events::RoutedEventArgs,
+    properties::{PropertyChangedHandler, PropertyValue},
+    ui_elements::{UIElementRef, UITreeRef},
+};
 
 
 pub trait CallableByName {
-    fn call_method(&self, name: &str);
+    // 'sender' is the element that raised the event, handlers declared
+    // without arguments are called without it. The code-behind is locked
+    // for the call, so handlers may change its state
+    fn call_method(&mut self, name: &str, sender: &UIElementRef, args: &RoutedEventArgs);
     fn get_tree(&self) -> Option<UITreeRef>;
     fn set_tree(&mut self, tree: Option<UITreeRef>);
+
+    // the code-behind is the data context of '{Binding}' paths, it has no
+    // bindable properties unless it provides them
+    fn get_property(&self, _name: &str) -> Option<PropertyValue> {
+        None
+    }
+
+    // returns false when there is no such writable property
+    fn set_property(&mut self, _name: &str, _value: PropertyValue) -> bool {
+        false
+    }
+
+    // handlers run with the code-behind locked, they must not lock it again
+    #[allow(dead_code)]
+    fn add_property_changed_handler(&mut self, _handler: PropertyChangedHandler) {}
+
+    // tells the handlers about a property changed by the code-behind itself,
+    // set_property does so on its own
+    #[allow(dead_code)]
+    fn property_changed(&self, _name: &str) {}
 }
 
 pub type MainCallable = &'static Mutex<dyn CallableByName>;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use ramaui::inspectable;
+
+    use crate::callable::CallableByName;
+    use crate::properties::PropertyValue;
+
+    #[inspectable]
+    struct Counter {
+        #[property]
+        click_count: i32,
+        #[property(name = "Title")]
+        caption: String,
+        #[property(readonly)]
+        ratio: f64,
+        hidden: bool,
+    }
+
+    #[inspectable(fields)]
+    impl Counter {
+        pub fn reset(&self) {}
+
+        #[property]
+        pub fn doubled(&self) -> i32 {
+            self.click_count * 2
+        }
+
+        pub fn set_doubled(&mut self, v: i32) {
+            self.click_count = v / 2;
+        }
+
+        #[property]
+        pub fn summary(&self) -> String {
+            format!("{}: {}", self.caption, self.click_count)
+        }
+    }
+
+    // the document's Click="Button_Click" is checked while compiling
+    #[inspectable(xaml = "tests/button-test.xaml")]
+    impl ButtonPage {
+        pub fn button_click(&mut self) {}
+    }
+
+    #[test]
+    fn handler_names_ignore_case_and_underscores() {
+        let page = ButtonPage::new();
+        for n in ["Button_Click", "ButtonClick", "button_click", "BUTTON_CLICK"] {
+            assert_eq!(page.find_method(n).unwrap().name, "button_click");
+        }
+        assert!(page.find_method("Button_Clik").is_none());
+    }
+
+    #[test]
+    fn properties_are_exposed_by_name() {
+        let mut c = Counter::new();
+        assert_eq!(c.info.len(), 1);
+        assert_eq!(c.get_property("ClickCount"), Some(PropertyValue::Int(0)));
+        assert_eq!(c.get_property("click_count"), Some(PropertyValue::Int(0)));
+        assert!(c.get_property("Hidden").is_none() && !c.hidden);
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = changes.clone();
+        c.add_property_changed_handler(Box::new(move |name, v| seen.lock().push(format!("{}={}", name, v))));
+
+        assert!(c.set_property("ClickCount", PropertyValue::Str("3".to_string())));
+        assert!(c.set_property("ClickCount", PropertyValue::Int(3)));
+        assert!(!c.set_property("ClickCount", PropertyValue::Str("many".to_string())));
+        assert!(c.set_property("Title", PropertyValue::Str("Clicks".to_string())));
+        assert!(!c.set_property("Ratio", PropertyValue::Float(0.5)));
+        assert_eq!(c.get_property("Ratio"), Some(PropertyValue::Float(0.0)));
+
+        assert_eq!(c.get_property("Doubled"), Some(PropertyValue::Int(6)));
+        assert!(c.set_property("Doubled", PropertyValue::Int(10)));
+        assert_eq!(c.get_property("ClickCount"), Some(PropertyValue::Int(5)));
+        assert!(!c.set_property("Summary", PropertyValue::Str("x".to_string())));
+
+        c.property_changed("Summary");
+        assert_eq!(*changes.lock(), vec!["ClickCount=3", "Title=Clicks", "Doubled=10", "Summary=Clicks: 5"]);
+    }
+}
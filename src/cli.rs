@@ -0,0 +1,286 @@
+use crate::layout::Size;
+use crate::xaml_format::DEFAULT_WIDTH;
+
+pub const USAGE: &str = "USAGE: ramaui [--log SPEC] [--strict] <file.xaml>
+       ramaui render <file.xaml> -o <out.png|out.svg> [--size WxH]
+       ramaui tui <file.xaml>
+       ramaui export --html|--xaml <file.xaml> [-o <out>]
+       ramaui fmt [--check] [--width N] <file.xaml>...
+       ramaui dump [--json] <file.xaml>
+
+SPEC enables logging, as in RAMAUI_LOG=events=debug,parser=trace
+--strict reports unknown elements as errors instead of loading placeholders";
+
+#[derive(Debug, PartialEq)]
+pub enum ExportFormat {
+    Html,
+    Xaml,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    // open the document in a GTK window
+    Run {
+        file: String,
+    },
+    Render {
+        file: String,
+        output: String,
+        size: Option<Size>,
+    },
+    // run the document in the terminal
+    Tui {
+        file: String,
+    },
+    // write the document in another format, to stdout without an output
+    Export {
+        file: String,
+        format: ExportFormat,
+        output: Option<String>,
+    },
+    // print the parsed tree, as JSON or as debug lines
+    Dump {
+        file: String,
+        json: bool,
+    },
+    // reformat files in place, or only report the unformatted ones
+    Fmt {
+        files: Vec<String>,
+        check: bool,
+        width: usize,
+    },
+}
+
+// the largest width or height of a rendered image
+pub const MAX_SIZE: u32 = 16384;
+
+// "800x600"
+pub fn parse_size(s: &str) -> Result<Size, String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("invalid size '{}', expected WIDTHxHEIGHT", s))?;
+    match (w.trim().parse::<u32>(), h.trim().parse::<u32>()) {
+        (Ok(w), Ok(h)) if w > MAX_SIZE || h > MAX_SIZE => {
+            Err(format!("invalid size '{}', at most {}x{}", s, MAX_SIZE, MAX_SIZE))
+        }
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok(Size::new(w as f64, h as f64)),
+        _ => Err(format!("invalid size '{}', expected WIDTHxHEIGHT", s)),
+    }
+}
+
+fn option_value<'a>(args: &mut impl Iterator<Item = &'a String>, option: &str) -> Result<&'a String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} needs a value", option))
+}
+
+// removes '--log SPEC' or '--log=SPEC' from anywhere in 'args'
+pub fn take_log_spec(args: &mut Vec<String>) -> Result<Option<String>, String> {
+    let mut spec = None;
+    let mut i = 0;
+    while i < args.len() {
+        if let Some(s) = args[i].strip_prefix("--log=") {
+            spec = Some(s.to_string());
+            args.remove(i);
+        } else if args[i] == "--log" {
+            if i + 1 >= args.len() {
+                return Err("--log needs a value".to_string());
+            }
+            spec = Some(args.remove(i + 1));
+            args.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    Ok(spec)
+}
+
+// removes every 'flag' from 'args', true when there was one
+pub fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+// 'args' excludes the program name
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut it = args.iter();
+    let first = it.next().ok_or_else(|| "no XAML file given".to_string())?;
+
+    match first.as_str() {
+        "render" => {
+            let mut file = None;
+            let mut output = None;
+            let mut size = None;
+            while let Some(a) = it.next() {
+                match a.as_str() {
+                    "-o" | "--output" => output = Some(option_value(&mut it, a)?.to_string()),
+                    "--size" => size = Some(parse_size(option_value(&mut it, a)?)?),
+                    _ if a.starts_with('-') => return Err(format!("unknown option '{}'", a)),
+                    _ if file.is_none() => file = Some(a.to_string()),
+                    _ => return Err(format!("unexpected argument '{}'", a)),
+                }
+            }
+            Ok(Command::Render {
+                file: file.ok_or_else(|| "no XAML file given".to_string())?,
+                output: output.ok_or_else(|| "no output file given, use -o".to_string())?,
+                size,
+            })
+        }
+        "tui" => {
+            let file = it.next().ok_or_else(|| "no XAML file given".to_string())?;
+            if let Some(a) = it.next() {
+                return Err(format!("unexpected argument '{}'", a));
+            }
+            Ok(Command::Tui {
+                file: file.to_string(),
+            })
+        }
+        "export" => {
+            let mut file = None;
+            let mut format = None;
+            let mut output = None;
+            while let Some(a) = it.next() {
+                match a.as_str() {
+                    "--html" => format = Some(ExportFormat::Html),
+                    "--xaml" => format = Some(ExportFormat::Xaml),
+                    "-o" | "--output" => output = Some(option_value(&mut it, a)?.to_string()),
+                    _ if a.starts_with('-') => return Err(format!("unknown option '{}'", a)),
+                    _ if file.is_none() => file = Some(a.to_string()),
+                    _ => return Err(format!("unexpected argument '{}'", a)),
+                }
+            }
+            Ok(Command::Export {
+                file: file.ok_or_else(|| "no XAML file given".to_string())?,
+                format: format.ok_or_else(|| "no export format given, use --html or --xaml".to_string())?,
+                output,
+            })
+        }
+        "dump" => {
+            let mut file = None;
+            let mut json = false;
+            for a in it {
+                match a.as_str() {
+                    "--json" => json = true,
+                    _ if a.starts_with('-') => return Err(format!("unknown option '{}'", a)),
+                    _ if file.is_none() => file = Some(a.to_string()),
+                    _ => return Err(format!("unexpected argument '{}'", a)),
+                }
+            }
+            Ok(Command::Dump {
+                file: file.ok_or_else(|| "no XAML file given".to_string())?,
+                json,
+            })
+        }
+        "fmt" => {
+            let mut files = Vec::new();
+            let mut check = false;
+            let mut width = DEFAULT_WIDTH;
+            while let Some(a) = it.next() {
+                match a.as_str() {
+                    "--check" => check = true,
+                    "--width" => {
+                        let v = option_value(&mut it, a)?;
+                        width = v
+                            .parse()
+                            .map_err(|_| format!("invalid width '{}'", v))?;
+                    }
+                    _ if a.starts_with('-') => return Err(format!("unknown option '{}'", a)),
+                    _ => files.push(a.to_string()),
+                }
+            }
+            if files.is_empty() {
+                return Err("no XAML file given".to_string());
+            }
+            Ok(Command::Fmt {
+                files,
+                check,
+                width,
+            })
+        }
+        _ => {
+            if let Some(a) = it.next() {
+                return Err(format!("unexpected argument '{}'", a));
+            }
+            Ok(Command::Run {
+                file: first.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{parse_args, take_flag, take_log_spec, Command, ExportFormat};
+    use crate::layout::Size;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse_args(&args("tests/grid-test.xaml")),
+            Ok(Command::Run {
+                file: "tests/grid-test.xaml".to_string()
+            })
+        );
+        assert_eq!(
+            parse_args(&args("render a.xaml -o out.png --size 800x600")),
+            Ok(Command::Render {
+                file: "a.xaml".to_string(),
+                output: "out.png".to_string(),
+                size: Some(Size::new(800.0, 600.0)),
+            })
+        );
+        assert_eq!(
+            parse_args(&args("tui a.xaml")),
+            Ok(Command::Tui {
+                file: "a.xaml".to_string()
+            })
+        );
+        assert_eq!(
+            parse_args(&args("export --html a.xaml")),
+            Ok(Command::Export {
+                file: "a.xaml".to_string(),
+                format: ExportFormat::Html,
+                output: None,
+            })
+        );
+        assert_eq!(
+            parse_args(&args("export --xaml a.xaml -o b.xaml")),
+            Ok(Command::Export {
+                file: "a.xaml".to_string(),
+                format: ExportFormat::Xaml,
+                output: Some("b.xaml".to_string()),
+            })
+        );
+        assert!(parse_args(&args("export a.xaml")).is_err());
+        assert_eq!(
+            parse_args(&args("dump --json a.xaml")),
+            Ok(Command::Dump {
+                file: "a.xaml".to_string(),
+                json: true,
+            })
+        );
+        assert_eq!(
+            parse_args(&args("fmt --check --width 80 a.xaml b.xaml")),
+            Ok(Command::Fmt {
+                files: vec!["a.xaml".to_string(), "b.xaml".to_string()],
+                check: true,
+                width: 80,
+            })
+        );
+        assert!(parse_args(&args("render a.xaml")).is_err());
+
+        let mut a = args("--log events=debug render a.xaml --log=trace -o x.png");
+        assert_eq!(take_log_spec(&mut a), Ok(Some("trace".to_string())));
+        assert_eq!(a, args("render a.xaml -o x.png"));
+        assert!(take_log_spec(&mut args("a.xaml --log")).is_err());
+        let mut a = args("--strict tui a.xaml");
+        assert!(take_flag(&mut a, "--strict"));
+        assert_eq!(a, args("tui a.xaml"));
+        assert!(!take_flag(&mut a, "--strict"));
+        assert!(parse_args(&args("render a.xaml -o x.png --size 800")).is_err());
+    }
+}
@@ -1,33 +1,50 @@
 use std::collections::HashMap;
 
-use crate::{events::Event, ui_elements::{get_attribute, tabs, UIAlloc, UICommon, UIElement, UIElementRef}, visitor::Visitor};
+use crate::{properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue}, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup}, visitor::Visitor};
 
 
 pub struct ContentPage {
-    title: String,
     common: UICommon,
 }
 
+static CONTENT_PAGE_PROPERTIES: [PropertyDef; 1] =
+    [PropertyDef::new("Title", PropertyKind::Str, "Title")];
 
 impl UIAlloc for ContentPage {
     fn new(attributes: &HashMap<String, String>, id: String) -> ContentPage {
         ContentPage {
-            title: get_attribute(&attributes, "Title", "Title"),
-            common: UICommon::new(attributes, "ContentPage", id),
+            common: UICommon::new(attributes, "ContentPage", id, &CONTENT_PAGE_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &CONTENT_PAGE_PROPERTIES
+    }
+}
+
+impl ContentPage {
+    pub fn get_title(&self) -> String {
+        self.common.get_str("Title")
+    }
 }
 
 impl UIElement for ContentPage {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
 
@@ -35,8 +52,32 @@ impl UIElement for ContentPage {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "ContentPage"
@@ -44,12 +85,15 @@ impl UIElement for ContentPage {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!(
             "{}DUMP: {}  -  title:{}",
             tabs(indent),
             self.get_ui_type_name(),
-            self.title
+            self.get_title()
         );
         self.common.dump(indent);
     }
@@ -0,0 +1,485 @@
+use std::fmt;
+
+// the converters turn XAML attribute text into typed values, the error
+// strings end up as the reason of XamlError::InvalidAttribute
+
+fn parse_number(s: &str) -> Result<f64, String> {
+    let v = s.trim();
+    match v.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(format!("'{}' is not a number", v)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Thickness {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Thickness {
+    pub fn uniform(v: f64) -> Thickness {
+        Thickness {
+            left: v,
+            top: v,
+            right: v,
+            bottom: v,
+        }
+    }
+
+    // "4", "4,2" (left and right, top and bottom) or "1,2,3,4"
+    // (left, top, right, bottom), spaces may separate the values as well
+    pub fn parse(s: &str) -> Result<Thickness, String> {
+        let mut parts = Vec::new();
+        for p in s.split(|c: char| c == ',' || c.is_whitespace()) {
+            if p.is_empty() {
+                continue;
+            }
+            parts.push(parse_number(p)?);
+        }
+        match parts.len() {
+            1 => Ok(Thickness::uniform(parts[0])),
+            2 => Ok(Thickness {
+                left: parts[0],
+                top: parts[1],
+                right: parts[0],
+                bottom: parts[1],
+            }),
+            4 => Ok(Thickness {
+                left: parts[0],
+                top: parts[1],
+                right: parts[2],
+                bottom: parts[3],
+            }),
+            n => Err(format!("expected 1, 2 or 4 lengths but got {}", n)),
+        }
+    }
+
+    pub fn horizontal(&self) -> f64 {
+        self.left + self.right
+    }
+
+    pub fn vertical(&self) -> f64 {
+        self.top + self.bottom
+    }
+}
+
+impl fmt::Display for Thickness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.left == self.right && self.top == self.bottom {
+            if self.left == self.top {
+                return write!(f, "{}", self.left);
+            }
+            return write!(f, "{},{}", self.left, self.top);
+        }
+        write!(f, "{},{},{},{}", self.left, self.top, self.right, self.bottom)
+    }
+}
+
+// size of a row or column: 'Auto', a pixel value or a weighted '*'
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridLength {
+    Auto,
+    Pixel(f64),
+    Star(f64),
+}
+
+impl GridLength {
+    pub fn parse(s: &str) -> Result<GridLength, String> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(GridLength::Auto);
+        }
+        if let Some(weight) = s.strip_suffix('*') {
+            if weight.trim().is_empty() {
+                return Ok(GridLength::Star(1.0));
+            }
+            return match parse_number(weight) {
+                Ok(w) if w >= 0.0 => Ok(GridLength::Star(w)),
+                _ => Err(format!("'{}' is not a valid star weight", weight.trim())),
+            };
+        }
+        match parse_number(s) {
+            Ok(px) if px >= 0.0 => Ok(GridLength::Pixel(px)),
+            Ok(_) => Err("a length can not be negative".to_string()),
+            Err(_) => Err(format!(
+                "'{}' is not 'Auto', a number of pixels or a star size",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GridLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridLength::Auto => f.write_str("Auto"),
+            GridLength::Pixel(px) => write!(f, "{}", px),
+            GridLength::Star(w) if *w == 1.0 => f.write_str("*"),
+            GridLength::Star(w) => write!(f, "{}*", w),
+        }
+    }
+}
+
+// how a colour was written, it is written back the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpelling {
+    Name(&'static str),
+    // '#RGB', '#ARGB', '#RRGGBB' or '#AARRGGBB'
+    Hex(u8),
+}
+
+// colours are equal when their channels are, however they are spelled
+#[derive(Debug, Clone, Copy)]
+pub struct Color {
+    pub a: u8,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub spelling: ColorSpelling,
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Color) -> bool {
+        self.to_argb() == other.to_argb()
+    }
+}
+
+impl Eq for Color {}
+
+// System.Windows.Media.Colors, as 0xAARRGGBB
+const NAMED_COLORS: [(&str, u32); 141] = [
+    ("AliceBlue", 0xFFF0F8FF),
+    ("AntiqueWhite", 0xFFFAEBD7),
+    ("Aqua", 0xFF00FFFF),
+    ("Aquamarine", 0xFF7FFFD4),
+    ("Azure", 0xFFF0FFFF),
+    ("Beige", 0xFFF5F5DC),
+    ("Bisque", 0xFFFFE4C4),
+    ("Black", 0xFF000000),
+    ("BlanchedAlmond", 0xFFFFEBCD),
+    ("Blue", 0xFF0000FF),
+    ("BlueViolet", 0xFF8A2BE2),
+    ("Brown", 0xFFA52A2A),
+    ("BurlyWood", 0xFFDEB887),
+    ("CadetBlue", 0xFF5F9EA0),
+    ("Chartreuse", 0xFF7FFF00),
+    ("Chocolate", 0xFFD2691E),
+    ("Coral", 0xFFFF7F50),
+    ("CornflowerBlue", 0xFF6495ED),
+    ("Cornsilk", 0xFFFFF8DC),
+    ("Crimson", 0xFFDC143C),
+    ("Cyan", 0xFF00FFFF),
+    ("DarkBlue", 0xFF00008B),
+    ("DarkCyan", 0xFF008B8B),
+    ("DarkGoldenrod", 0xFFB8860B),
+    ("DarkGray", 0xFFA9A9A9),
+    ("DarkGreen", 0xFF006400),
+    ("DarkKhaki", 0xFFBDB76B),
+    ("DarkMagenta", 0xFF8B008B),
+    ("DarkOliveGreen", 0xFF556B2F),
+    ("DarkOrange", 0xFFFF8C00),
+    ("DarkOrchid", 0xFF9932CC),
+    ("DarkRed", 0xFF8B0000),
+    ("DarkSalmon", 0xFFE9967A),
+    ("DarkSeaGreen", 0xFF8FBC8F),
+    ("DarkSlateBlue", 0xFF483D8B),
+    ("DarkSlateGray", 0xFF2F4F4F),
+    ("DarkTurquoise", 0xFF00CED1),
+    ("DarkViolet", 0xFF9400D3),
+    ("DeepPink", 0xFFFF1493),
+    ("DeepSkyBlue", 0xFF00BFFF),
+    ("DimGray", 0xFF696969),
+    ("DodgerBlue", 0xFF1E90FF),
+    ("Firebrick", 0xFFB22222),
+    ("FloralWhite", 0xFFFFFAF0),
+    ("ForestGreen", 0xFF228B22),
+    ("Fuchsia", 0xFFFF00FF),
+    ("Gainsboro", 0xFFDCDCDC),
+    ("GhostWhite", 0xFFF8F8FF),
+    ("Gold", 0xFFFFD700),
+    ("Goldenrod", 0xFFDAA520),
+    ("Gray", 0xFF808080),
+    ("Green", 0xFF008000),
+    ("GreenYellow", 0xFFADFF2F),
+    ("Honeydew", 0xFFF0FFF0),
+    ("HotPink", 0xFFFF69B4),
+    ("IndianRed", 0xFFCD5C5C),
+    ("Indigo", 0xFF4B0082),
+    ("Ivory", 0xFFFFFFF0),
+    ("Khaki", 0xFFF0E68C),
+    ("Lavender", 0xFFE6E6FA),
+    ("LavenderBlush", 0xFFFFF0F5),
+    ("LawnGreen", 0xFF7CFC00),
+    ("LemonChiffon", 0xFFFFFACD),
+    ("LightBlue", 0xFFADD8E6),
+    ("LightCoral", 0xFFF08080),
+    ("LightCyan", 0xFFE0FFFF),
+    ("LightGoldenrodYellow", 0xFFFAFAD2),
+    ("LightGray", 0xFFD3D3D3),
+    ("LightGreen", 0xFF90EE90),
+    ("LightPink", 0xFFFFB6C1),
+    ("LightSalmon", 0xFFFFA07A),
+    ("LightSeaGreen", 0xFF20B2AA),
+    ("LightSkyBlue", 0xFF87CEFA),
+    ("LightSlateGray", 0xFF778899),
+    ("LightSteelBlue", 0xFFB0C4DE),
+    ("LightYellow", 0xFFFFFFE0),
+    ("Lime", 0xFF00FF00),
+    ("LimeGreen", 0xFF32CD32),
+    ("Linen", 0xFFFAF0E6),
+    ("Magenta", 0xFFFF00FF),
+    ("Maroon", 0xFF800000),
+    ("MediumAquamarine", 0xFF66CDAA),
+    ("MediumBlue", 0xFF0000CD),
+    ("MediumOrchid", 0xFFBA55D3),
+    ("MediumPurple", 0xFF9370DB),
+    ("MediumSeaGreen", 0xFF3CB371),
+    ("MediumSlateBlue", 0xFF7B68EE),
+    ("MediumSpringGreen", 0xFF00FA9A),
+    ("MediumTurquoise", 0xFF48D1CC),
+    ("MediumVioletRed", 0xFFC71585),
+    ("MidnightBlue", 0xFF191970),
+    ("MintCream", 0xFFF5FFFA),
+    ("MistyRose", 0xFFFFE4E1),
+    ("Moccasin", 0xFFFFE4B5),
+    ("NavajoWhite", 0xFFFFDEAD),
+    ("Navy", 0xFF000080),
+    ("OldLace", 0xFFFDF5E6),
+    ("Olive", 0xFF808000),
+    ("OliveDrab", 0xFF6B8E23),
+    ("Orange", 0xFFFFA500),
+    ("OrangeRed", 0xFFFF4500),
+    ("Orchid", 0xFFDA70D6),
+    ("PaleGoldenrod", 0xFFEEE8AA),
+    ("PaleGreen", 0xFF98FB98),
+    ("PaleTurquoise", 0xFFAFEEEE),
+    ("PaleVioletRed", 0xFFDB7093),
+    ("PapayaWhip", 0xFFFFEFD5),
+    ("PeachPuff", 0xFFFFDAB9),
+    ("Peru", 0xFFCD853F),
+    ("Pink", 0xFFFFC0CB),
+    ("Plum", 0xFFDDA0DD),
+    ("PowderBlue", 0xFFB0E0E6),
+    ("Purple", 0xFF800080),
+    ("Red", 0xFFFF0000),
+    ("RosyBrown", 0xFFBC8F8F),
+    ("RoyalBlue", 0xFF4169E1),
+    ("SaddleBrown", 0xFF8B4513),
+    ("Salmon", 0xFFFA8072),
+    ("SandyBrown", 0xFFF4A460),
+    ("SeaGreen", 0xFF2E8B57),
+    ("SeaShell", 0xFFFFF5EE),
+    ("Sienna", 0xFFA0522D),
+    ("Silver", 0xFFC0C0C0),
+    ("SkyBlue", 0xFF87CEEB),
+    ("SlateBlue", 0xFF6A5ACD),
+    ("SlateGray", 0xFF708090),
+    ("Snow", 0xFFFFFAFA),
+    ("SpringGreen", 0xFF00FF7F),
+    ("SteelBlue", 0xFF4682B4),
+    ("Tan", 0xFFD2B48C),
+    ("Teal", 0xFF008080),
+    ("Thistle", 0xFFD8BFD8),
+    ("Tomato", 0xFFFF6347),
+    ("Transparent", 0x00FFFFFF),
+    ("Turquoise", 0xFF40E0D0),
+    ("Violet", 0xFFEE82EE),
+    ("Wheat", 0xFFF5DEB3),
+    ("White", 0xFFFFFFFF),
+    ("WhiteSmoke", 0xFFF5F5F5),
+    ("Yellow", 0xFFFFFF00),
+    ("YellowGreen", 0xFF9ACD32),
+];
+
+impl Color {
+    pub const fn from_argb(argb: u32) -> Color {
+        Color {
+            a: (argb >> 24) as u8,
+            r: (argb >> 16) as u8,
+            g: (argb >> 8) as u8,
+            b: argb as u8,
+            spelling: ColorSpelling::Hex(8),
+        }
+    }
+
+    fn spelled(self, spelling: ColorSpelling) -> Color {
+        Color { spelling, ..self }
+    }
+
+    pub fn to_argb(self) -> u32 {
+        (self.a as u32) << 24 | (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    // a colour name, '#RGB', '#ARGB', '#RRGGBB' or '#AARRGGBB'
+    pub fn parse(s: &str) -> Result<Color, String> {
+        let v = s.trim();
+        let hex = match v.strip_prefix('#') {
+            Some(hex) => hex,
+            None => {
+                return NAMED_COLORS
+                    .iter()
+                    .find(|(name, _)| v.eq_ignore_ascii_case(name))
+                    .map(|(name, argb)| Color::from_argb(*argb).spelled(ColorSpelling::Name(name)))
+                    .ok_or_else(|| format!("'{}' is not a known colour name", v));
+            }
+        };
+
+        let digits = match u32::from_str_radix(hex, 16) {
+            Ok(d) if !hex.starts_with('+') => d,
+            _ => return Err(format!("'{}' is not a hexadecimal colour", v)),
+        };
+        // short forms repeat every digit: #F80 is #FF8800
+        let expand = |d: u32| -> u32 { (d & 0xF) * 0x11 };
+        let color = match hex.len() {
+            3 => Color::from_argb(
+                0xFF000000 | expand(digits >> 8) << 16 | expand(digits >> 4) << 8 | expand(digits),
+            ),
+            4 => Color::from_argb(
+                expand(digits >> 12) << 24
+                    | expand(digits >> 8) << 16
+                    | expand(digits >> 4) << 8
+                    | expand(digits),
+            ),
+            6 => Color::from_argb(0xFF000000 | digits),
+            8 => Color::from_argb(digits),
+            n => {
+                return Err(format!(
+                    "'{}' has {} digits, expected #RGB, #ARGB, #RRGGBB or #AARRGGBB",
+                    v, n
+                ))
+            }
+        };
+        Ok(color.spelled(ColorSpelling::Hex(hex.len() as u8)))
+    }
+
+    // CSS wants the alpha last, so the colour is always written as rgba()
+    pub fn to_css(self) -> String {
+        format!(
+            "rgba({},{},{},{})",
+            self.r,
+            self.g,
+            self.b,
+            (self.a as f64 / 255.0 * 1000.0).round() / 1000.0
+        )
+    }
+}
+
+// spelled as it was parsed, the short hex forms only while they still fit
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let argb = self.to_argb();
+        let short = [self.a, self.r, self.g, self.b].iter().all(|c| c >> 4 == c & 0xF);
+        let nibbles = |n: u32| (0..n).rev().map(|i| format!("{:X}", argb >> (i * 8) & 0xF)).collect::<String>();
+        match self.spelling {
+            ColorSpelling::Name(name) => f.write_str(name),
+            ColorSpelling::Hex(3) if short && self.a == 255 => write!(f, "#{}", nibbles(3)),
+            ColorSpelling::Hex(4) if short => write!(f, "#{}", nibbles(4)),
+            ColorSpelling::Hex(6) if self.a == 255 => write!(f, "#{:06X}", argb & 0xFFFFFF),
+            _ => write!(f, "#{:08X}", argb),
+        }
+    }
+}
+
+// an enum whose members are spelled in XAML as they are in Rust,
+// NAMES feeds PropertyKind::Enum
+macro_rules! xaml_enum {
+    ($(#[$attr:meta])* $name:ident { $($member:ident),+ $(,)? }) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($member),+
+        }
+
+        $(#[$attr])*
+        impl $name {
+            pub const NAMES: &'static [&'static str] = &[$(stringify!($member)),+];
+
+            pub fn parse(s: &str) -> Result<$name, String> {
+                let v = s.trim();
+                $(
+                    if v.eq_ignore_ascii_case(stringify!($member)) {
+                        return Ok($name::$member);
+                    }
+                )+
+                Err(format!(
+                    "'{}' is not a {}, expected one of {}",
+                    v,
+                    stringify!($name),
+                    $name::NAMES.join(", ")
+                ))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let name = match self {
+                    $($name::$member => stringify!($member)),+
+                };
+                f.write_str(name)
+            }
+        }
+    };
+}
+
+xaml_enum!(HorizontalAlignment { Left, Center, Right, Stretch });
+xaml_enum!(VerticalAlignment { Top, Center, Bottom, Stretch });
+// only its NAMES are used, elements keep the visibility as text
+xaml_enum!(#[allow(dead_code)] Visibility { Visible, Hidden, Collapsed });
+xaml_enum!(Orientation { Vertical, Horizontal });
+xaml_enum!(BindingMode { OneWay, TwoWay, OneTime });
+
+#[cfg(test)]
+mod tests {
+    use crate::converters::{Color, GridLength, HorizontalAlignment, Thickness};
+
+    #[test]
+    fn parses_thickness_and_grid_lengths() {
+        assert_eq!(Thickness::parse("4").unwrap(), Thickness::uniform(4.0));
+        let t = Thickness::parse("4,2").unwrap();
+        assert_eq!((t.left, t.top, t.right, t.bottom), (4.0, 2.0, 4.0, 2.0));
+        assert_eq!(t.to_string(), "4,2");
+        assert_eq!(Thickness::parse("1, 2,3,4").unwrap().to_string(), "1,2,3,4");
+        assert!(Thickness::parse("1,2,3").unwrap_err().contains("got 3"));
+        assert!(Thickness::parse("1,x").unwrap_err().contains("'x'"));
+
+        assert_eq!(GridLength::parse("Auto"), Ok(GridLength::Auto));
+        assert_eq!(GridLength::parse("*"), Ok(GridLength::Star(1.0)));
+        assert_eq!(GridLength::parse(" 2* "), Ok(GridLength::Star(2.0)));
+        assert_eq!(GridLength::parse("45"), Ok(GridLength::Pixel(45.0)));
+        assert!(GridLength::parse("wide").is_err());
+        assert!(GridLength::parse("x*").unwrap_err().contains("star weight"));
+    }
+
+    #[test]
+    fn parses_colors() {
+        let steel = Color::parse("LightSteelBlue").unwrap();
+        assert_eq!(steel.to_argb(), 0xFFB0C4DE);
+        assert_eq!(steel.to_string(), "LightSteelBlue");
+        assert_eq!(Color::parse("lightsteelblue"), Ok(steel));
+        assert_eq!(Color::parse("#F80").unwrap().to_argb(), 0xFFFF8800);
+        assert_eq!(Color::parse("#8F80").unwrap().to_argb(), 0x88FF8800);
+        assert_eq!(Color::parse("#123456").unwrap().to_string(), "#123456");
+        assert_eq!(Color::parse("#ff0000").unwrap().to_string(), "#FF0000");
+        assert_eq!(Color::parse("#8f80").unwrap().to_string(), "#8F80");
+        assert_eq!(Color::parse("Cyan").unwrap().to_string(), "Cyan");
+        assert_eq!(Color::parse("Cyan"), Color::parse("Aqua"));
+        assert_eq!(Color::from_argb(0xFFFF0000).to_string(), "#FFFF0000");
+        assert_eq!(Color::parse("#80FF0000").unwrap().to_css(), "rgba(255,0,0,0.502)");
+        assert_eq!(Color::parse("Transparent").unwrap().a, 0);
+        assert!(Color::parse("Bleu").unwrap_err().contains("colour name"));
+        assert!(Color::parse("#12345").unwrap_err().contains("5 digits"));
+        assert!(Color::parse("#GG0000").is_err());
+    }
+
+    #[test]
+    fn parses_enums() {
+        assert_eq!(HorizontalAlignment::parse("center"), Ok(HorizontalAlignment::Center));
+        assert_eq!(HorizontalAlignment::Stretch.to_string(), "Stretch");
+        assert!(HorizontalAlignment::parse("Top")
+            .unwrap_err()
+            .contains("expected one of Left, Center, Right, Stretch"));
+    }
+}
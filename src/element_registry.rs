@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::{
+    button::Button,
+    content_page::ContentPage,
+    grid_layout::{
+        ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition,
+    },
+    label::Label,
+    properties::PropertyDef,
+    stack_layout::StackLayout,
+    text_block::TextBlock,
+    ui_elements::{UIAlloc, UIElement, UIElementRef},
+    window::Window,
+};
+
+pub type ElementFactory = fn(&HashMap<String, String>, String) -> UIElementRef;
+
+pub fn static_leaker<T: UIElement + UIAlloc + 'static>(
+    attributes: &HashMap<String, String>,
+    id: String,
+) -> UIElementRef {
+    let inner = T::new(attributes, id);
+    Arc::new(Mutex::new(inner))
+}
+
+#[derive(Clone, Copy)]
+pub struct RegisteredElement {
+    pub factory: ElementFactory,
+    // what the reader validates the element's attributes against
+    pub properties: &'static [PropertyDef],
+}
+
+// maps XAML tag names, optionally qualified by an XML namespace, to the
+// UIElement types the reader instantiates for them
+pub struct ElementRegistry {
+    elements: HashMap<(Option<String>, String), RegisteredElement>,
+}
+
+impl ElementRegistry {
+    pub fn new() -> ElementRegistry {
+        ElementRegistry {
+            elements: HashMap::new(),
+        }
+    }
+
+    pub fn with_builtins() -> ElementRegistry {
+        let mut r = ElementRegistry::new();
+        r.register::<Label>("Label");
+        r.register::<ContentPage>("ContentPage");
+        r.register::<Button>("Button");
+        r.register::<Window>("Window");
+        r.register::<GridLayout>("Grid");
+        r.register::<StackLayout>("StackPanel");
+        r.register::<GridColumnDefinitions>("Grid.ColumnDefinitions");
+        r.register::<GridRowDefinitions>("Grid.RowDefinitions");
+        r.register::<ColumnDefinition>("ColumnDefinition");
+        r.register::<RowDefinition>("RowDefinition");
+        r.register::<TextBlock>("TextBlock");
+        r
+    }
+
+    // registers a type under the default namespace, the one the built-in
+    // elements and unprefixed tags in WPF/MAUI documents live in
+    pub fn register<T: UIElement + UIAlloc + 'static>(&mut self, name: &str) {
+        self.register_factory(None, name, static_leaker::<T>, T::properties());
+    }
+
+    // 'namespace' is the code namespace of a 'clr-namespace:' or 'using:'
+    // mapping, or the URI of any other xmlns, see XamlNamespace::registry_key
+    pub fn register_factory(
+        &mut self,
+        namespace: Option<&str>,
+        name: &str,
+        factory: ElementFactory,
+        properties: &'static [PropertyDef],
+    ) {
+        self.elements.insert(
+            (namespace.map(|n| n.to_string()), name.to_string()),
+            RegisteredElement {
+                factory,
+                properties,
+            },
+        );
+    }
+
+    pub fn lookup(&self, namespace: Option<&str>, name: &str) -> Option<RegisteredElement> {
+        let key = (namespace.map(|n| n.to_string()), name.to_string());
+        self.elements.get(&key).copied()
+    }
+}
+
+impl Default for ElementRegistry {
+    fn default() -> Self {
+        ElementRegistry::with_builtins()
+    }
+}
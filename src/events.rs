@@ -1,24 +1,148 @@
+use log::{debug, trace, warn};
+
+use crate::binding;
 use crate::callable::MainCallable;
+use crate::logging::{DISPATCH, EVENTS};
+use crate::ui_elements::UIElementRef;
 
 
-pub struct Event {
-    name: String, 
-    mc: MainCallable
+// what a handler taking '(&self, sender: &UIElementRef, args: &RoutedEventArgs)'
+// learns about an event, the fields a backend can't fill in are None
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoutedEventArgs {
+    // 'Button.Click', 'KeyDown', ...
+    pub name: String,
+    // the id of the element that raised the event
+    pub source_id: String,
+    // the pointer position in the coordinates of the source element
+    pub position: Option<(f64, f64)>,
+    // the mouse button, 1 is the primary one
+    pub button: Option<u32>,
+    // the name of the key, such as 'Return', 'a' or 'F1'
+    pub key: Option<String>,
+    // the new text of an element whose text was edited
+    pub text: Option<String>,
 }
 
-impl Event {
-    pub fn new(name: &str, mc: MainCallable) -> Event {
-        Event {
+impl RoutedEventArgs {
+    pub fn new(name: &str, source_id: &str) -> RoutedEventArgs {
+        RoutedEventArgs {
             name: name.to_string(),
-            mc
+            source_id: source_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// delivers the event 'ev_name' raised by a backend widget to the element
+// with id 'id', from where it bubbles up to a handler
+pub fn dispatch_event(mw: MainCallable, id: &str,
+    ev_name: &str)
+{
+    dispatch_routed_event(mw, RoutedEventArgs::new(ev_name, id));
+}
+
+// no element is locked while the handler runs, so it can use the sender
+pub fn dispatch_routed_event(mw: MainCallable, args: RoutedEventArgs) {
+    let mut source: Option<UIElementRef> = Option::None;
+
+    {
+        let k = mw.lock();
+        if let Some(tree) = k.get_tree() {
+            source = tree.find_by_id(args.source_id.to_string());
+        } else {
+            warn!(target: DISPATCH, "no tree to deliver {} to", args.name);
         }
     }
 
-    pub fn get_callable(&self) -> MainCallable {
-        return self.mc;
+    let Some(sender) = source else {
+        warn!(target: DISPATCH, "no element {} for {}", args.source_id, args.name);
+        return;
+    };
+    debug!(target: DISPATCH, "{} on {} {}", args.name, sender.lock().get_ui_type_name(), args.source_id);
+
+    let mut current = Some(sender.clone());
+    while let Some(elt) = current {
+        let (handler, parent) = {
+            let k = elt.lock();
+            trace!(target: EVENTS, "{} at {} {}", args.name, k.get_ui_type_name(), k.get_id());
+            (k.get_handler_name(&args.name), k.get_parent())
+        };
+        if let Some(h) = handler {
+            debug!(target: EVENTS, "{} handled by {}", args.name, h);
+            mw.lock().call_method(&h, &sender, &args);
+            break;
+        }
+        current = parent;
+    }
+
+    // the handler may have changed the data context
+    binding::sync(mw);
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+    use ramaui::inspectable;
+
+    use crate::callable::CallableByName;
+    use crate::events::{dispatch_event, dispatch_routed_event, RoutedEventArgs};
+    use crate::properties::PropertyValue;
+    use crate::ui_elements::UIElementRef;
+    use crate::xaml_reader;
+
+    #[inspectable]
+    struct Form {
+        calls: Mutex<Vec<String>>,
+        #[property]
+        count: i32,
+    }
+
+    #[inspectable(fields)]
+    impl Form {
+        pub fn on_ok(&self) {
+            self.calls.lock().push("on_ok".to_string());
+        }
+
+        pub fn on_any(&self, sender: &UIElementRef, args: &RoutedEventArgs) {
+            // nothing is locked while handlers run
+            let sender = sender.lock();
+            self.calls.lock().push(format!("on_any {} {} {:?}", sender.get_ui_type_name(), args.name, args.position));
+        }
+
+        pub fn on_count(&mut self) {
+            self.count += 1;
+        }
+
+        pub fn on_count_by(&mut self, _sender: &UIElementRef, args: &RoutedEventArgs) {
+            self.count += args.key.as_ref().map(|k| k.len() as i32).unwrap_or(0);
+        }
     }
 
-    pub fn get_name(&self) -> &String {
-        return &self.name;
+    #[test]
+    fn handlers_get_sender_and_args() {
+        let text = "<Window KeyDown=\"on_count_by\"><StackPanel Button.Click=\"on_any\">\
+                    <Button Click=\"on_ok\">Ok</Button><Button>Other</Button>\
+                    <Button Click=\"on_count\">Count</Button></StackPanel></Window>";
+        let tree = Box::leak(Box::new(xaml_reader::read_xaml_str("inline", text).unwrap()));
+        let form = Box::leak(Box::new(Mutex::new(Form::new())));
+        form.lock().set_tree(Some(tree));
+
+        dispatch_event(form, "ID_2", "Button.Click");
+        let mut args = RoutedEventArgs::new("Button.Click", "ID_3");
+        args.position = Some((4.0, 2.0));
+        dispatch_routed_event(form, args);
+        dispatch_event(form, "ID_4", "Button.Click");
+        dispatch_event(form, "ID_4", "Button.Click");
+        let mut args = RoutedEventArgs::new("KeyDown", "ID_0");
+        args.key = Some("Return".to_string());
+        dispatch_routed_event(form, args);
+        dispatch_event(form, "ID_1", "KeyDown");
+
+        assert_eq!(
+            *form.lock().calls.lock(),
+            vec!["on_ok", "on_any Button Button.Click Some((4.0, 2.0))"]
+        );
+        assert_eq!(form.lock().get_property("Count"), Some(PropertyValue::Int(8)));
     }
-}
\ No newline at end of file
+}
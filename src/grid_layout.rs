@@ -1,16 +1,58 @@
 use std::collections::HashMap;
 
 use crate::{
-    events::Event,
-    ui_elements::{get_attribute, tabs, UIAlloc, UICommon, UIElement, UIElementRef},
+    converters::{Color, GridLength},
+    properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue},
+    ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup},
     visitor::Visitor,
 };
 
 pub struct GridLayout {
     common: UICommon,
-    _name: String,
-    _show_grid_lines: String,
-    _background: String,
+}
+
+// rows and columns default to '*' when not given
+fn definition_length(def: &dyn UIElement, prop: &str) -> GridLength {
+    def.get_value(prop)
+        .and_then(|v| v.as_grid_length())
+        .unwrap_or(GridLength::Star(1.0))
+}
+
+static GRID_PROPERTIES: [PropertyDef; 2] = [
+    PropertyDef::unset("Background", PropertyKind::Color),
+    PropertyDef::new("ShowGridLines", PropertyKind::Bool, "False"),
+];
+
+static COLUMN_DEFINITION_PROPERTIES: [PropertyDef; 1] =
+    [PropertyDef::new("Width", PropertyKind::GridLength, "*")];
+
+static ROW_DEFINITION_PROPERTIES: [PropertyDef; 1] =
+    [PropertyDef::new("Height", PropertyKind::GridLength, "*")];
+
+// the Grid.Row, Grid.Column, Grid.RowSpan and Grid.ColumnSpan attached
+// properties of an element, only looked at when its parent is a Grid
+pub struct GridPlacement {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+}
+
+impl GridPlacement {
+    pub fn from_element(elt: &dyn UIElement) -> GridPlacement {
+        let get = |prop: &str, default: usize| -> usize {
+            match elt.get_value(prop).and_then(|v| v.as_i32()) {
+                Some(v) if v >= 0 => v as usize,
+                _ => default,
+            }
+        };
+        GridPlacement {
+            row: get("Grid.Row", 0),
+            column: get("Grid.Column", 0),
+            row_span: get("Grid.RowSpan", 1).max(1),
+            column_span: get("Grid.ColumnSpan", 1).max(1),
+        }
+    }
 }
 
 pub struct GridColumnDefinitions {
@@ -31,33 +73,116 @@ pub struct RowDefinition {
 impl UIAlloc for GridLayout {
     fn new(attributes: &HashMap<String, String>, id: String) -> GridLayout {
         GridLayout {
-            _name: get_attribute(&attributes, "Name", ""),
-            _background: get_attribute(&attributes, "Background", ""),
-            _show_grid_lines: get_attribute(&attributes, "ShowGridLines", ""),
-            common: UICommon::new(attributes, "Grid", id),
+            common: UICommon::new(attributes, "Grid", id, &GRID_PROPERTIES),
+        }
+    }
+
+    fn properties() -> &'static [PropertyDef] {
+        &GRID_PROPERTIES
+    }
+}
+
+impl GridLayout {
+    pub fn get_background(&self) -> Option<Color> {
+        self.common.get_value("Background").and_then(|v| v.as_color())
+    }
+
+    pub fn shows_grid_lines(&self) -> bool {
+        self.common.get_bool("ShowGridLines").unwrap_or(false)
+    }
+
+    #[allow(dead_code)]
+    pub fn get_width(&self) -> Option<i32> {
+        self.common.get_width()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_height(&self) -> Option<i32> {
+        self.common.get_height()
+    }
+
+    // a grid without definitions has a single star sized row
+    pub fn get_row_definitions(&self) -> Vec<GridLength> {
+        grid_definitions(&self.common.get_children(), false)
+    }
+
+    pub fn get_column_definitions(&self) -> Vec<GridLength> {
+        grid_definitions(&self.common.get_children(), true)
+    }
+}
+
+// the row or column sizes declared by the children of a Grid
+pub fn grid_definitions(grid_children: &[UIElementRef], columns: bool) -> Vec<GridLength> {
+    let (container_type, prop) = if columns {
+        ("Grid_ColumnDefinitions", "Width")
+    } else {
+        ("Grid_RowDefinitions", "Height")
+    };
+    let mut ret = Vec::new();
+    for c in grid_children.iter() {
+        let k = c.lock();
+        if k.get_ui_type_name() != container_type {
+            continue;
+        }
+        for def in k.get_children().iter() {
+            ret.push(definition_length(&*def.lock(), prop));
         }
     }
+    if ret.is_empty() {
+        ret.push(GridLength::Star(1.0));
+    }
+    ret
 }
 
 impl UIElement for GridLayout {
     fn get_id(&self) -> String {
         self.common.get_id()
+    }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
     }    fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
 
-    fn handle_event(&self, ev: Event) {
-        println!("NOTICE: grid-handle-event");
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "Grid"
@@ -65,6 +190,9 @@ impl UIElement for GridLayout {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -81,7 +209,7 @@ impl UIElement for GridLayout {
 impl UIAlloc for GridColumnDefinitions {
     fn new(attributes: &HashMap<String, String>, id: String) -> GridColumnDefinitions {
         GridColumnDefinitions {
-            common: UICommon::new(attributes, "GridColumnDefinitions", id),
+            common: UICommon::new(attributes, "GridColumnDefinitions", id, &[]),
         }
     }
 }
@@ -90,20 +218,51 @@ impl UIElement for GridColumnDefinitions {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "Grid_ColumnDefinitions"
@@ -111,6 +270,9 @@ impl UIElement for GridColumnDefinitions {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -127,7 +289,7 @@ impl UIElement for GridColumnDefinitions {
 impl UIAlloc for GridRowDefinitions {
     fn new(attributes: &HashMap<String, String>, id: String) -> GridRowDefinitions {
         GridRowDefinitions {
-            common: UICommon::new(attributes, "GridRowDefinitions", id),
+            common: UICommon::new(attributes, "GridRowDefinitions", id, &[]),
         }
     }
 }
@@ -136,20 +298,51 @@ impl UIElement for GridRowDefinitions {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "Grid_RowDefinitions"
@@ -157,6 +350,9 @@ impl UIElement for GridRowDefinitions {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -173,28 +369,63 @@ impl UIElement for GridRowDefinitions {
 impl UIAlloc for ColumnDefinition {
     fn new(attributes: &HashMap<String, String>, id: String) -> ColumnDefinition {
         ColumnDefinition {
-            common: UICommon::new(attributes, "ColumnDefinition", id),
+            common: UICommon::new(attributes, "ColumnDefinition", id, &COLUMN_DEFINITION_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &COLUMN_DEFINITION_PROPERTIES
+    }
 }
 impl UIElement for ColumnDefinition {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "ColumnDefinition"
@@ -202,6 +433,9 @@ impl UIElement for ColumnDefinition {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -218,28 +452,63 @@ impl UIElement for ColumnDefinition {
 impl UIAlloc for RowDefinition {
     fn new(attributes: &HashMap<String, String>, id: String) -> RowDefinition {
         RowDefinition {
-            common: UICommon::new(attributes, "RowDefinition", id),
+            common: UICommon::new(attributes, "RowDefinition", id, &ROW_DEFINITION_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &ROW_DEFINITION_PROPERTIES
+    }
 }
 impl UIElement for RowDefinition {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "RowDefinition"
@@ -247,6 +516,9 @@ impl UIElement for RowDefinition {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -0,0 +1,432 @@
+use std::fs;
+
+use crate::{
+    button::Button,
+    content_page::ContentPage,
+    converters::{GridLength, HorizontalAlignment, Thickness, VerticalAlignment},
+    grid_layout::{ColumnDefinition, GridColumnDefinitions, GridLayout, GridPlacement, GridRowDefinitions, RowDefinition},
+    label::Label,
+    stack_layout::StackLayout,
+    text_block::TextBlock,
+    ui_elements::{LayoutProps, UIElement, UITree},
+    unknown_ui_elt::Unknown,
+    visitor::Visitor,
+    window::Window,
+};
+
+// the static HTML backend: every element becomes one HTML element whose
+// inline style reproduces its layout, Grid and StackPanel map onto CSS grid
+// and flexbox
+
+const STYLESHEET: &str = "* { box-sizing: border-box; margin: 0; }
+html, body { height: 100%; }
+body { font-family: sans-serif; font-size: 12px; }
+button { font: inherit; }
+";
+
+// how a container places its children, decides the CSS of each child
+#[derive(Clone, Copy, PartialEq)]
+enum Placement {
+    // a CSS grid, single celled for Window, ContentPage and unknown elements
+    Grid { lines: bool },
+    Stack { horizontal: bool },
+    None,
+}
+
+struct Open {
+    tag: &'static str,
+    children: Placement,
+    // the Button.Click handler set on this element, if any
+    handler: Option<String>,
+}
+
+pub struct HtmlExporter {
+    out: String,
+    open: Vec<Open>,
+    title: Option<String>,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn css_length(v: f64) -> String {
+    format!("{}px", v)
+}
+
+fn css_thickness(t: Thickness) -> String {
+    format!(
+        "{}px {}px {}px {}px",
+        t.top, t.right, t.bottom, t.left
+    )
+}
+
+fn css_track(l: GridLength) -> String {
+    match l {
+        GridLength::Auto => "auto".to_string(),
+        GridLength::Pixel(px) => css_length(px),
+        GridLength::Star(w) => format!("{}fr", w),
+    }
+}
+
+fn css_tracks(lengths: &[GridLength]) -> String {
+    lengths.iter().map(|l| css_track(*l)).collect::<Vec<_>>().join(" ")
+}
+
+fn horizontal_self(a: HorizontalAlignment) -> &'static str {
+    match a {
+        HorizontalAlignment::Left => "start",
+        HorizontalAlignment::Center => "center",
+        HorizontalAlignment::Right => "end",
+        HorizontalAlignment::Stretch => "stretch",
+    }
+}
+
+fn vertical_self(a: VerticalAlignment) -> &'static str {
+    match a {
+        VerticalAlignment::Top => "start",
+        VerticalAlignment::Center => "center",
+        VerticalAlignment::Bottom => "end",
+        VerticalAlignment::Stretch => "stretch",
+    }
+}
+
+impl HtmlExporter {
+    pub fn new() -> HtmlExporter {
+        HtmlExporter {
+            out: String::new(),
+            open: Vec::new(),
+            title: None,
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.open.len() {
+            self.out.push_str("  ");
+        }
+    }
+
+    // the declarations every element gets from its common properties and
+    // from the container it is in
+    fn common_css(&self, elt: &dyn UIElement) -> Vec<String> {
+        let mut css = Vec::new();
+        let l = LayoutProps::from_element(elt);
+        if let Some(w) = l.width {
+            css.push(format!("width: {}", css_length(w)));
+        }
+        if let Some(h) = l.height {
+            css.push(format!("height: {}", css_length(h)));
+        }
+        if l.min_width > 0.0 {
+            css.push(format!("min-width: {}", css_length(l.min_width)));
+        }
+        if l.max_width.is_finite() {
+            css.push(format!("max-width: {}", css_length(l.max_width)));
+        }
+        if l.min_height > 0.0 {
+            css.push(format!("min-height: {}", css_length(l.min_height)));
+        }
+        if l.max_height.is_finite() {
+            css.push(format!("max-height: {}", css_length(l.max_height)));
+        }
+        if l.margin != Thickness::uniform(0.0) {
+            css.push(format!("margin: {}", css_thickness(l.margin)));
+        }
+        if l.padding != Thickness::uniform(0.0) {
+            css.push(format!("padding: {}", css_thickness(l.padding)));
+        }
+
+        let parent = self.open.last().map(|o| o.children).unwrap_or(Placement::None);
+        match parent {
+            Placement::Grid { lines } => {
+                let p = GridPlacement::from_element(elt);
+                css.push(format!("grid-row: {} / span {}", p.row + 1, p.row_span.max(1)));
+                css.push(format!("grid-column: {} / span {}", p.column + 1, p.column_span.max(1)));
+                if l.horizontal_alignment != HorizontalAlignment::Stretch {
+                    css.push(format!("justify-self: {}", horizontal_self(l.horizontal_alignment)));
+                }
+                if l.vertical_alignment != VerticalAlignment::Stretch {
+                    css.push(format!("align-self: {}", vertical_self(l.vertical_alignment)));
+                }
+                if lines {
+                    css.push("outline: 1px dashed #606060".to_string());
+                }
+            }
+            // only the cross axis alignment applies in a stack
+            Placement::Stack { horizontal } => {
+                let a = if horizontal {
+                    vertical_self(l.vertical_alignment)
+                } else {
+                    horizontal_self(l.horizontal_alignment)
+                };
+                if a != "stretch" {
+                    css.push(format!("align-self: {}", a));
+                }
+            }
+            Placement::None => {}
+        }
+
+        if let Some(c) = elt.get_value("Background").and_then(|v| v.as_color()) {
+            css.push(format!("background-color: {}", c.to_css()));
+        }
+        match elt.get_value("Visibility").map(|v| v.to_string()).as_deref() {
+            Some("Collapsed") => css.push("display: none".to_string()),
+            Some("Hidden") => css.push("visibility: hidden".to_string()),
+            _ => {}
+        }
+        css
+    }
+
+    // writes the start tag of 'elt', 'css' comes after the common declarations
+    fn open(
+        &mut self,
+        elt: &dyn UIElement,
+        tag: &'static str,
+        css: Vec<String>,
+        attributes: Vec<(&str, String)>,
+        children: Placement,
+    ) {
+        let mut style = self.common_css(elt);
+        style.extend(css);
+
+        self.indent();
+        self.out.push_str(&format!("<{} class=\"{}\"", tag, elt.get_ui_type_name()));
+        if let Some(name) = elt.get_name() {
+            self.out.push_str(&format!(" id=\"{}\"", escape(&name)));
+        }
+        if let Some(tip) = elt.get_value("ToolTip") {
+            self.out.push_str(&format!(" title=\"{}\"", escape(&tip.to_string())));
+        }
+        for (k, v) in attributes.iter() {
+            self.out.push_str(&format!(" {}=\"{}\"", k, escape(v)));
+        }
+        if !style.is_empty() {
+            self.out.push_str(&format!(" style=\"{}\"", escape(&style.join("; "))));
+        }
+        self.out.push_str(">\n");
+
+        self.open.push(Open {
+            tag,
+            children,
+            handler: elt.get_value("Button.Click").map(|v| v.to_string()),
+        });
+    }
+
+    fn text(&mut self, text: &str) {
+        self.indent();
+        self.out.push_str(&escape(text));
+        self.out.push('\n');
+    }
+
+    fn close(&mut self) {
+        if let Some(o) = self.open.pop() {
+            self.indent();
+            self.out.push_str(&format!("</{}>\n", o.tag));
+        }
+    }
+
+    // a single celled grid, so the alignment of the content works as in a
+    // Grid
+    fn open_content_host(&mut self, elt: &dyn UIElement, attributes: Vec<(&str, String)>) {
+        let css = vec![
+            "display: grid".to_string(),
+            "grid-template: 1fr / 1fr".to_string(),
+        ];
+        self.open(elt, "div", css, attributes, Placement::Grid { lines: false });
+    }
+
+    // the handler a click on the Button would reach, the event bubbles up
+    // to the first element with a Button.Click handler
+    fn click_handler(&self, b: &Button) -> Option<String> {
+        if let Some(h) = b.get_value("Click") {
+            return Some(h.to_string());
+        }
+        self.open.iter().rev().find_map(|o| o.handler.clone())
+    }
+
+    // the finished page
+    pub fn get_html(&self) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape(self.title.as_deref().unwrap_or("")),
+            STYLESHEET,
+            self.out
+        )
+    }
+}
+
+impl Visitor for HtmlExporter {
+    fn start_visit_button(&mut self, b: &Button) {
+        let mut attributes = Vec::new();
+        if let Some(h) = self.click_handler(b) {
+            attributes.push(("data-click", h));
+        }
+        if b.get_value("IsEnabled").and_then(|v| v.as_bool()) == Some(false) {
+            attributes.push(("disabled", "disabled".to_string()));
+        }
+        self.open(b, "button", Vec::new(), attributes, Placement::None);
+        self.text(&b.get_text());
+    }
+    fn start_visit_window(&mut self, w: &Window) {
+        let title = w.get_title();
+        if self.title.is_none() && !title.is_empty() {
+            self.title = Some(title);
+        }
+        self.open_content_host(w, Vec::new());
+    }
+    fn start_visit_label(&mut self, l: &Label) {
+        self.open(l, "label", Vec::new(), Vec::new(), Placement::None);
+        self.text(&l.get_content());
+    }
+    fn start_visit_text_block(&mut self, t: &TextBlock) {
+        let mut css = Vec::new();
+        if let Some(size) = t.get_font_size() {
+            css.push(format!("font-size: {}", css_length(size)));
+        }
+        if let Some(weight) = t.get_font_weight() {
+            css.push(format!("font-weight: {}", weight));
+        }
+        if let Some(c) = t.get_foreground() {
+            css.push(format!("color: {}", c.to_css()));
+        }
+        let align = t.get_text_alignment().to_ascii_lowercase();
+        if !align.is_empty() && align != "left" {
+            css.push(format!("text-align: {}", align));
+        }
+        if t.wraps() {
+            css.push("white-space: pre-wrap".to_string());
+        } else {
+            css.push("white-space: pre".to_string());
+        }
+        if t.trims() {
+            css.push("overflow: hidden".to_string());
+            css.push("text-overflow: ellipsis".to_string());
+        }
+        self.open(t, "div", css, Vec::new(), Placement::None);
+        self.text(&t.get_text());
+    }
+    fn start_visit_grid(&mut self, g: &GridLayout) {
+        let lines = g.shows_grid_lines();
+        let mut css = vec![
+            "display: grid".to_string(),
+            format!("grid-template-columns: {}", css_tracks(&g.get_column_definitions())),
+            format!("grid-template-rows: {}", css_tracks(&g.get_row_definitions())),
+        ];
+        if lines {
+            css.push("outline: 1px dashed #606060".to_string());
+        }
+        self.open(g, "div", css, Vec::new(), Placement::Grid { lines });
+    }
+    fn start_visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn start_visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn start_visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn start_visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn start_visit_content_page(&mut self, g: &ContentPage) {
+        if self.title.is_none() {
+            self.title = Some(g.get_title());
+        }
+        self.open_content_host(g, Vec::new());
+    }
+    fn start_visit_unknown(&mut self, g: &Unknown) {
+        let attributes = vec![("data-xaml-type", g.get_ui_type_name().to_string())];
+        self.open_content_host(g, attributes);
+    }
+    fn start_visit_stack(&mut self, g: &StackLayout) {
+        let horizontal = g.is_horizontal();
+        let mut css = vec![
+            "display: flex".to_string(),
+            format!("flex-direction: {}", if horizontal { "row" } else { "column" }),
+        ];
+        let spacing = g.get_spacing();
+        if spacing > 0.0 {
+            css.push(format!("gap: {}", css_length(spacing)));
+        }
+        self.open(g, "div", css, Vec::new(), Placement::Stack { horizontal });
+    }
+
+    fn visit_button(&mut self, _b: &Button) {
+        self.close();
+    }
+    fn visit_window(&mut self, _w: &Window) {
+        self.close();
+    }
+    fn visit_label(&mut self, _l: &Label) {
+        self.close();
+    }
+    fn visit_text_block(&mut self, _t: &TextBlock) {
+        self.close();
+    }
+    fn visit_grid(&mut self, _g: &GridLayout) {
+        self.close();
+    }
+    fn visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn visit_content_page(&mut self, _g: &ContentPage) {
+        self.close();
+    }
+    fn visit_unknown(&mut self, _g: &Unknown) {
+        self.close();
+    }
+    fn visit_stack(&mut self, _g: &StackLayout) {
+        self.close();
+    }
+}
+
+pub fn to_html(tree: &UITree) -> Result<String, String> {
+    let root = match &tree.root {
+        Some(r) => r,
+        None => return Err("the document has no root element".to_string()),
+    };
+    let mut exporter = HtmlExporter::new();
+    root.lock().visit(&mut exporter);
+    Ok(exporter.get_html())
+}
+
+// writes the page to 'output', or to stdout without one
+pub fn export_html(tree: &UITree, output: Option<&str>) -> Result<(), String> {
+    let html = to_html(tree)?;
+    match output {
+        Some(path) => fs::write(path, html).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            print!("{}", html);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::html_export::to_html;
+    use crate::xaml_reader;
+
+    #[test]
+    fn exports_grid_stack_and_handlers() {
+        let text = "<Window Title=\"A &amp; B\" Width=\"200\" Height=\"100\" Button.Click=\"on_any\">\
+                    <Grid><Grid.ColumnDefinitions><ColumnDefinition Width=\"40\"/><ColumnDefinition Width=\"2*\"/>\
+                    <ColumnDefinition Width=\"Auto\"/></Grid.ColumnDefinitions>\
+                    <StackPanel Grid.Column=\"1\" Orientation=\"Horizontal\" Spacing=\"4\">\
+                    <Button Click=\"on_ok\">Ok</Button><Button>Cancel</Button></StackPanel>\
+                    <TextBlock Grid.Column=\"2\" FontSize=\"14\" FontWeight=\"Bold\" Foreground=\"Red\" Text=\"a&lt;b\"/>\
+                    </Grid></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let html = to_html(&t).unwrap();
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("grid-template-columns: 40px 2fr auto; grid-template-rows: 1fr"));
+        assert!(html.contains("grid-row: 1 / span 1; grid-column: 2 / span 1; display: flex; flex-direction: row; gap: 4px"));
+        assert!(html.contains("<button class=\"Button\" data-click=\"on_ok\">"));
+        assert!(html.contains("<button class=\"Button\" data-click=\"on_any\">"));
+        assert!(html.contains("font-size: 14px; font-weight: 700; color: rgba(255,0,0,1)"));
+        assert!(html.contains("a&lt;b"));
+
+        // unstyled text keeps the 12px of the page stylesheet
+        let t = xaml_reader::read_xaml_str("inline", "<TextBlock Text=\"plain\"/>").unwrap();
+        let html = to_html(&t).unwrap();
+        assert_eq!(html.matches("font-size").count(), 1);
+        assert!(!html.contains("font-weight"));
+    }
+}
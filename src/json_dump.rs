@@ -0,0 +1,213 @@
+use serde_json::{json, Map, Number, Value};
+
+use crate::{
+    button::Button,
+    content_page::ContentPage,
+    grid_layout::{ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition},
+    label::Label,
+    properties::PropertyValue,
+    stack_layout::StackLayout,
+    text_block::TextBlock,
+    ui_elements::{UIElement, UIElementRef, UITree},
+    unknown_ui_elt::Unknown,
+    visitor::Visitor,
+    window::Window,
+};
+
+// a structured alternative to UIElement::dump, every element becomes
+// {"type", "id", "name", "properties", "children"}
+
+fn property_json(v: &PropertyValue) -> Value {
+    match v {
+        PropertyValue::Int(i) => json!(i),
+        PropertyValue::Bool(b) => json!(b),
+        // NaN and the infinities, which JSON numbers can not hold
+        PropertyValue::Float(f) => match Number::from_f64(*f) {
+            Some(n) => Value::Number(n),
+            None => Value::String(v.to_string()),
+        },
+        _ => Value::String(v.to_string()),
+    }
+}
+
+pub struct JsonDumper {
+    // the elements being visited, the innermost last
+    open: Vec<Value>,
+    root: Option<Value>,
+}
+
+impl JsonDumper {
+    pub fn new() -> JsonDumper {
+        JsonDumper {
+            open: Vec::new(),
+            root: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_json(&self) -> Option<&Value> {
+        self.root.as_ref()
+    }
+
+    fn begin(&mut self, elt: &dyn UIElement) {
+        let mut properties = Map::new();
+        for (name, v) in elt.get_values().iter() {
+            properties.insert(name.to_string(), property_json(v));
+        }
+        self.open.push(json!({
+            "type": elt.get_ui_type_name(),
+            "id": elt.get_id(),
+            "name": elt.get_name(),
+            "properties": properties,
+            "children": [],
+        }));
+    }
+
+    fn end(&mut self) {
+        let done = match self.open.pop() {
+            Some(v) => v,
+            None => return,
+        };
+        match self.open.last_mut() {
+            Some(parent) => {
+                if let Some(Value::Array(children)) = parent.get_mut("children") {
+                    children.push(done);
+                }
+            }
+            None => self.root = Some(done),
+        }
+    }
+}
+
+impl Visitor for JsonDumper {
+    fn start_visit_button(&mut self, b: &Button) {
+        self.begin(b);
+    }
+    fn start_visit_window(&mut self, w: &Window) {
+        self.begin(w);
+    }
+    fn start_visit_label(&mut self, l: &Label) {
+        self.begin(l);
+    }
+    fn start_visit_text_block(&mut self, t: &TextBlock) {
+        self.begin(t);
+    }
+    fn start_visit_grid(&mut self, g: &GridLayout) {
+        self.begin(g);
+    }
+    fn start_visit_grid_cols(&mut self, g: &GridColumnDefinitions) {
+        self.begin(g);
+    }
+    fn start_visit_grid_row(&mut self, g: &GridRowDefinitions) {
+        self.begin(g);
+    }
+    fn start_visit_col_def(&mut self, g: &ColumnDefinition) {
+        self.begin(g);
+    }
+    fn start_visit_row_def(&mut self, g: &RowDefinition) {
+        self.begin(g);
+    }
+    fn start_visit_content_page(&mut self, g: &ContentPage) {
+        self.begin(g);
+    }
+    fn start_visit_unknown(&mut self, g: &Unknown) {
+        self.begin(g);
+    }
+    fn start_visit_stack(&mut self, g: &StackLayout) {
+        self.begin(g);
+    }
+
+    fn visit_button(&mut self, _b: &Button) {
+        self.end();
+    }
+    fn visit_window(&mut self, _w: &Window) {
+        self.end();
+    }
+    fn visit_label(&mut self, _l: &Label) {
+        self.end();
+    }
+    fn visit_text_block(&mut self, _t: &TextBlock) {
+        self.end();
+    }
+    fn visit_grid(&mut self, _g: &GridLayout) {
+        self.end();
+    }
+    fn visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {
+        self.end();
+    }
+    fn visit_grid_row(&mut self, _g: &GridRowDefinitions) {
+        self.end();
+    }
+    fn visit_col_def(&mut self, _g: &ColumnDefinition) {
+        self.end();
+    }
+    fn visit_row_def(&mut self, _g: &RowDefinition) {
+        self.end();
+    }
+    fn visit_content_page(&mut self, _g: &ContentPage) {
+        self.end();
+    }
+    fn visit_unknown(&mut self, _g: &Unknown) {
+        self.end();
+    }
+    fn visit_stack(&mut self, _g: &StackLayout) {
+        self.end();
+    }
+}
+
+pub fn element_to_json(elt: &UIElementRef) -> Value {
+    let mut dumper = JsonDumper::new();
+    elt.lock().visit(&mut dumper);
+    dumper.root.unwrap_or(Value::Null)
+}
+
+pub fn tree_to_json(tree: &UITree) -> Result<Value, String> {
+    match &tree.root {
+        Some(root) => Ok(element_to_json(root)),
+        None => Err("the document has no root element".to_string()),
+    }
+}
+
+// pretty printed, with a trailing newline
+pub fn dump_json(tree: &UITree) -> Result<String, String> {
+    let v = tree_to_json(tree)?;
+    let text = serde_json::to_string_pretty(&v).map_err(|e| e.to_string())?;
+    Ok(text + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::json_dump::tree_to_json;
+    use crate::xaml_reader;
+
+    #[test]
+    fn dumps_tree_shape_and_properties() {
+        let text = "<Window Title=\"T\" Width=\"Auto\"><StackPanel Name=\"S\">\
+                    <Button IsEnabled=\"False\" Margin=\"4,2\">Ok</Button></StackPanel></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        assert_eq!(
+            tree_to_json(&t).unwrap(),
+            json!({
+                "type": "Window",
+                "id": "ID_0",
+                "name": null,
+                "properties": {"Title": "T", "Width": "Auto"},
+                "children": [{
+                    "type": "StackLayout",
+                    "id": "ID_1",
+                    "name": "S",
+                    "properties": {"Name": "S"},
+                    "children": [{
+                        "type": "Button",
+                        "id": "ID_2",
+                        "name": null,
+                        "properties": {"Content": "Ok", "IsEnabled": false, "Margin": "4,2"},
+                        "children": [],
+                    }],
+                }],
+            })
+        );
+    }
+}
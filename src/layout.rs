@@ -0,0 +1,654 @@
+use crate::{
+    converters::{GridLength, HorizontalAlignment, Orientation, Thickness, VerticalAlignment},
+    grid_layout::{grid_definitions, GridPlacement},
+    ui_elements::{LayoutProps, UIElementRef},
+};
+
+// a WPF style two pass layout: Measure asks every element how much room it
+// wants given what its parent can offer, Arrange then hands out the final
+// rectangles. It only reads the element tree, so any backend can use it.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Size {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Size {
+    pub fn new(width: f64, height: f64) -> Size {
+        Size { width, height }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn deflate(&self, t: Thickness) -> Rect {
+        Rect {
+            x: self.x + t.left,
+            y: self.y + t.top,
+            width: (self.width - t.horizontal()).max(0.0),
+            height: (self.height - t.vertical()).max(0.0),
+        }
+    }
+
+    pub fn right(&self) -> f64 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y + self.height
+    }
+}
+
+pub trait TextMeasurer {
+    // 'wrap_width' is None when the text stays on one line
+    fn measure(&self, text: &str, font_size: f64, wrap_width: Option<f64>) -> Size;
+}
+
+// every character is 0.6em wide and every line 1.2em high, deterministic
+// and close enough for the text based backends
+pub struct FixedWidthMeasurer;
+
+pub const CHAR_WIDTH: f64 = 0.6;
+pub const LINE_HEIGHT: f64 = 1.2;
+
+impl TextMeasurer for FixedWidthMeasurer {
+    fn measure(&self, text: &str, font_size: f64, wrap_width: Option<f64>) -> Size {
+        let char_width = font_size * CHAR_WIDTH;
+        let max_chars = wrap_width.map(|w| ((w / char_width).floor() as usize).max(1));
+        let lines = wrap_text(text, max_chars);
+        let longest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        Size::new(
+            longest as f64 * char_width,
+            lines.len().max(1) as f64 * font_size * LINE_HEIGHT,
+        )
+    }
+}
+
+// breaks at spaces where possible, words longer than a line are cut
+pub fn wrap_text(text: &str, max_chars: Option<usize>) -> Vec<String> {
+    let mut ret = Vec::new();
+    for paragraph in text.split('\n') {
+        let max = match max_chars {
+            Some(m) => m,
+            None => {
+                ret.push(paragraph.to_string());
+                continue;
+            }
+        };
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut word: Vec<char> = word.chars().collect();
+            let used = line.chars().count();
+            if used > 0 && used + 1 + word.len() <= max {
+                line.push(' ');
+                line.extend(word.iter());
+                continue;
+            }
+            if used > 0 {
+                ret.push(std::mem::take(&mut line));
+            }
+            while word.len() > max {
+                ret.push(word.drain(..max).collect());
+            }
+            line.extend(word.iter());
+        }
+        ret.push(line);
+    }
+    ret
+}
+
+enum Content {
+    Text {
+        text: String,
+        font_size: f64,
+        wrap: bool,
+    },
+    Stack {
+        orientation: Orientation,
+        spacing: f64,
+    },
+    Grid {
+        rows: Vec<GridLength>,
+        columns: Vec<GridLength>,
+        // the content sizes of the rows and columns found while measuring
+        row_sizes: Vec<f64>,
+        column_sizes: Vec<f64>,
+    },
+    // everything else stacks its children on top of each other
+    Panel,
+}
+
+// what the layout needs to know of an element, read once so the passes
+// below do not lock the tree
+struct Node {
+    id: String,
+    layout: LayoutProps,
+    collapsed: bool,
+    placement: GridPlacement,
+    content: Content,
+    children: Vec<Node>,
+    desired: Size,
+}
+
+fn is_definition(type_name: &str) -> bool {
+    matches!(
+        type_name,
+        "Grid_ColumnDefinitions" | "Grid_RowDefinitions" | "ColumnDefinition" | "RowDefinition"
+    )
+}
+
+fn snapshot(elt: &UIElementRef) -> Node {
+    let k = elt.lock();
+    let children = k.get_children();
+    let str_value = |name: &str| k.get_value(name).map(|v| v.to_string()).unwrap_or_default();
+    let font_size = k.get_value("FontSize").and_then(|v| v.as_f64()).unwrap_or(12.0);
+
+    let content = match k.get_ui_type_name() {
+        "TextBlock" => Content::Text {
+            text: str_value("Text"),
+            font_size,
+            wrap: str_value("TextWrapping").starts_with("Wrap"),
+        },
+        "Label" => Content::Text {
+            text: str_value("Text"),
+            font_size,
+            wrap: false,
+        },
+        "Button" => Content::Text {
+            text: str_value("Content"),
+            font_size,
+            wrap: false,
+        },
+        "StackLayout" => Content::Stack {
+            orientation: Orientation::parse(&str_value("Orientation"))
+                .unwrap_or(Orientation::Vertical),
+            spacing: k.get_value("Spacing").and_then(|v| v.as_f64()).unwrap_or(0.0),
+        },
+        "Grid" => Content::Grid {
+            rows: grid_definitions(&children, false),
+            columns: grid_definitions(&children, true),
+            row_sizes: Vec::new(),
+            column_sizes: Vec::new(),
+        },
+        _ => Content::Panel,
+    };
+
+    let mut node = Node {
+        id: k.get_id(),
+        layout: LayoutProps::from_element(&*k),
+        collapsed: str_value("Visibility") == "Collapsed",
+        placement: GridPlacement::from_element(&*k),
+        content,
+        children: Vec::new(),
+        desired: Size::new(0.0, 0.0),
+    };
+    drop(k);
+
+    for c in children.iter() {
+        if is_definition(c.lock().get_ui_type_name()) {
+            continue;
+        }
+        node.children.push(snapshot(c));
+    }
+    node
+}
+
+// the smallest and largest size allowed, an explicit Width or Height is
+// both but still has to respect MinWidth/MaxWidth
+fn min_max(len: Option<f64>, min: f64, max: f64) -> (f64, f64) {
+    match len {
+        Some(v) => (v.min(max).max(min), v.min(max).max(min)),
+        None => (min, max.max(min)),
+    }
+}
+
+fn track_measure_sizes(defs: &[GridLength], available: f64) -> Vec<f64> {
+    let fixed: f64 = defs
+        .iter()
+        .map(|d| match d {
+            GridLength::Pixel(px) => *px,
+            _ => 0.0,
+        })
+        .sum();
+    let stars: f64 = defs
+        .iter()
+        .map(|d| match d {
+            GridLength::Star(w) => *w,
+            _ => 0.0,
+        })
+        .sum();
+    defs.iter()
+        .map(|d| match d {
+            GridLength::Pixel(px) => *px,
+            GridLength::Auto => f64::INFINITY,
+            GridLength::Star(w) if available.is_finite() && stars > 0.0 => {
+                (available - fixed).max(0.0) * w / stars
+            }
+            GridLength::Star(_) => f64::INFINITY,
+        })
+        .collect()
+}
+
+// pixel tracks keep their size, auto tracks get their content size and the
+// star tracks share whatever is left by weight
+fn track_final_sizes(defs: &[GridLength], content_sizes: &[f64], available: f64) -> Vec<f64> {
+    let mut ret: Vec<f64> = defs
+        .iter()
+        .zip(content_sizes.iter())
+        .map(|(d, c)| match d {
+            GridLength::Pixel(px) => *px,
+            GridLength::Auto => *c,
+            GridLength::Star(_) => 0.0,
+        })
+        .collect();
+    let stars: f64 = defs
+        .iter()
+        .map(|d| match d {
+            GridLength::Star(w) => *w,
+            _ => 0.0,
+        })
+        .sum();
+    if stars > 0.0 {
+        let left = (available - ret.iter().sum::<f64>()).max(0.0);
+        for (i, d) in defs.iter().enumerate() {
+            if let GridLength::Star(w) = d {
+                ret[i] = left * w / stars;
+            }
+        }
+    }
+    ret
+}
+
+// the first track and the number of tracks a child occupies, out of range
+// positions end up in the last track as in WPF
+fn track_range(start: usize, span: usize, count: usize) -> (usize, usize) {
+    let first = start.min(count - 1);
+    (first, span.min(count - first))
+}
+
+fn offset_of(sizes: &[f64], index: usize) -> f64 {
+    sizes[..index].iter().sum()
+}
+
+fn track_edges(start: f64, sizes: &[f64]) -> Vec<f64> {
+    let mut ret = vec![start];
+    for s in sizes.iter() {
+        ret.push(ret.last().unwrap() + s);
+    }
+    ret
+}
+
+fn measure(node: &mut Node, available: Size, m: &dyn TextMeasurer) -> Size {
+    if node.collapsed {
+        node.desired = Size::new(0.0, 0.0);
+        return node.desired;
+    }
+    let lp = node.layout;
+    let (min_w, max_w) = min_max(lp.width, lp.min_width, lp.max_width);
+    let (min_h, max_h) = min_max(lp.height, lp.min_height, lp.max_height);
+
+    let inner = Size::new(
+        (available.width - lp.margin.horizontal()).min(max_w).max(min_w),
+        (available.height - lp.margin.vertical()).min(max_h).max(min_h),
+    );
+    let content_available = Size::new(
+        (inner.width - lp.padding.horizontal()).max(0.0),
+        (inner.height - lp.padding.vertical()).max(0.0),
+    );
+
+    let content = measure_content(node, content_available, m);
+    let width = (content.width + lp.padding.horizontal()).min(max_w).max(min_w);
+    let height = (content.height + lp.padding.vertical()).min(max_h).max(min_h);
+
+    node.desired = Size::new(
+        (width + lp.margin.horizontal()).min(available.width).max(0.0),
+        (height + lp.margin.vertical()).min(available.height).max(0.0),
+    );
+    node.desired
+}
+
+fn measure_content(node: &mut Node, available: Size, m: &dyn TextMeasurer) -> Size {
+    match &mut node.content {
+        Content::Text {
+            text,
+            font_size,
+            wrap,
+        } => {
+            let wrap_width = if *wrap && available.width.is_finite() {
+                Some(available.width)
+            } else {
+                None
+            };
+            m.measure(text, *font_size, wrap_width)
+        }
+        Content::Stack {
+            orientation,
+            spacing,
+        } => {
+            let horizontal = *orientation == Orientation::Horizontal;
+            let child_available = if horizontal {
+                Size::new(f64::INFINITY, available.height)
+            } else {
+                Size::new(available.width, f64::INFINITY)
+            };
+            let mut along: f64 = 0.0;
+            let mut across: f64 = 0.0;
+            let mut visible = 0;
+            for c in node.children.iter_mut() {
+                let d = measure(c, child_available, m);
+                if c.collapsed {
+                    continue;
+                }
+                visible += 1;
+                if horizontal {
+                    along += d.width;
+                    across = across.max(d.height);
+                } else {
+                    along += d.height;
+                    across = across.max(d.width);
+                }
+            }
+            if visible > 1 {
+                along += *spacing * (visible - 1) as f64;
+            }
+            if horizontal {
+                Size::new(along, across)
+            } else {
+                Size::new(across, along)
+            }
+        }
+        Content::Grid {
+            rows,
+            columns,
+            row_sizes,
+            column_sizes,
+        } => {
+            let row_available = track_measure_sizes(rows, available.height);
+            let column_available = track_measure_sizes(columns, available.width);
+            *row_sizes = vec![0.0; rows.len()];
+            *column_sizes = vec![0.0; columns.len()];
+
+            for c in node.children.iter_mut() {
+                let p = &c.placement;
+                let (row, row_span) = track_range(p.row, p.row_span, rows.len());
+                let (column, column_span) = track_range(p.column, p.column_span, columns.len());
+                let cell = Size::new(
+                    column_available[column..column + column_span].iter().sum(),
+                    row_available[row..row + row_span].iter().sum(),
+                );
+                let d = measure(c, cell, m);
+                // children spanning several tracks do not size them
+                if column_span == 1 {
+                    column_sizes[column] = column_sizes[column].max(d.width);
+                }
+                if row_span == 1 {
+                    row_sizes[row] = row_sizes[row].max(d.height);
+                }
+            }
+
+            let total = |defs: &[GridLength], sizes: &[f64]| -> f64 {
+                defs.iter()
+                    .zip(sizes.iter())
+                    .map(|(d, s)| match d {
+                        GridLength::Pixel(px) => *px,
+                        _ => *s,
+                    })
+                    .sum()
+            };
+            Size::new(total(columns, column_sizes), total(rows, row_sizes))
+        }
+        Content::Panel => {
+            let mut ret = Size::new(0.0, 0.0);
+            for c in node.children.iter_mut() {
+                let d = measure(c, available, m);
+                ret.width = ret.width.max(d.width);
+                ret.height = ret.height.max(d.height);
+            }
+            ret
+        }
+    }
+}
+
+fn collapse(node: &Node, at: Rect, out: &mut LayoutResult) {
+    out.rects.push((node.id.to_string(), Rect::new(at.x, at.y, 0.0, 0.0)));
+    for c in node.children.iter() {
+        collapse(c, at, out);
+    }
+}
+
+fn arrange(node: &Node, slot: Rect, out: &mut LayoutResult) {
+    if node.collapsed {
+        collapse(node, slot, out);
+        return;
+    }
+    let lp = node.layout;
+    let available = slot.deflate(lp.margin);
+    let (min_w, max_w) = min_max(lp.width, lp.min_width, lp.max_width);
+    let (min_h, max_h) = min_max(lp.height, lp.min_height, lp.max_height);
+
+    let desired_width = (node.desired.width - lp.margin.horizontal()).max(0.0);
+    let desired_height = (node.desired.height - lp.margin.vertical()).max(0.0);
+    let width = match (lp.width, lp.horizontal_alignment) {
+        (None, HorizontalAlignment::Stretch) => available.width,
+        _ => desired_width,
+    }
+    .min(max_w)
+    .max(min_w);
+    let height = match (lp.height, lp.vertical_alignment) {
+        (None, VerticalAlignment::Stretch) => available.height,
+        _ => desired_height,
+    }
+    .min(max_h)
+    .max(min_h);
+
+    // an explicitly sized element that is asked to stretch is centered
+    let x = match lp.horizontal_alignment {
+        HorizontalAlignment::Left => available.x,
+        HorizontalAlignment::Right => available.right() - width,
+        HorizontalAlignment::Center | HorizontalAlignment::Stretch => {
+            available.x + (available.width - width) / 2.0
+        }
+    };
+    let y = match lp.vertical_alignment {
+        VerticalAlignment::Top => available.y,
+        VerticalAlignment::Bottom => available.bottom() - height,
+        VerticalAlignment::Center | VerticalAlignment::Stretch => {
+            available.y + (available.height - height) / 2.0
+        }
+    };
+
+    let rect = Rect::new(x, y, width, height);
+    out.rects.push((node.id.to_string(), rect));
+    let content = rect.deflate(lp.padding);
+
+    match &node.content {
+        Content::Stack {
+            orientation,
+            spacing,
+        } => {
+            let horizontal = *orientation == Orientation::Horizontal;
+            let mut offset = 0.0;
+            for c in node.children.iter() {
+                if c.collapsed {
+                    collapse(c, content, out);
+                    continue;
+                }
+                if horizontal {
+                    let w = c.desired.width;
+                    arrange(c, Rect::new(content.x + offset, content.y, w, content.height), out);
+                    offset += w + spacing;
+                } else {
+                    let h = c.desired.height;
+                    arrange(c, Rect::new(content.x, content.y + offset, content.width, h), out);
+                    offset += h + spacing;
+                }
+            }
+        }
+        Content::Grid {
+            rows,
+            columns,
+            row_sizes,
+            column_sizes,
+        } => {
+            let heights = track_final_sizes(rows, row_sizes, content.height);
+            let widths = track_final_sizes(columns, column_sizes, content.width);
+            out.grids.push((
+                node.id.to_string(),
+                GridTracks {
+                    columns: track_edges(content.x, &widths),
+                    rows: track_edges(content.y, &heights),
+                },
+            ));
+            for c in node.children.iter() {
+                let p = &c.placement;
+                let (row, row_span) = track_range(p.row, p.row_span, rows.len());
+                let (column, column_span) = track_range(p.column, p.column_span, columns.len());
+                let cell = Rect::new(
+                    content.x + offset_of(&widths, column),
+                    content.y + offset_of(&heights, row),
+                    widths[column..column + column_span].iter().sum(),
+                    heights[row..row + row_span].iter().sum(),
+                );
+                arrange(c, cell, out);
+            }
+        }
+        Content::Text { .. } | Content::Panel => {
+            for c in node.children.iter() {
+                arrange(c, content, out);
+            }
+        }
+    }
+}
+
+// where the rows and columns of an arranged Grid start and end, n tracks
+// have n + 1 edges
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridTracks {
+    pub columns: Vec<f64>,
+    pub rows: Vec<f64>,
+}
+
+// the rectangle of every element by id, in document order
+pub struct LayoutResult {
+    rects: Vec<(String, Rect)>,
+    grids: Vec<(String, GridTracks)>,
+}
+
+impl LayoutResult {
+    pub fn get(&self, id: &str) -> Option<Rect> {
+        self.rects.iter().find(|(i, _)| i == id).map(|(_, r)| *r)
+    }
+
+    pub fn get_grid_tracks(&self, id: &str) -> Option<&GridTracks> {
+        self.grids.iter().find(|(i, _)| i == id).map(|(_, t)| t)
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Rect)> {
+        self.rects.iter()
+    }
+}
+
+// the Width and Height of a root Window, what a backend without a window
+// manager should use when it is not given a size
+pub fn window_size(root: &UIElementRef) -> Option<Size> {
+    let k = root.lock();
+    if k.get_ui_type_name() != "Window" {
+        return None;
+    }
+    let layout = LayoutProps::from_element(&*k);
+    Some(Size::new(layout.width?, layout.height?))
+}
+
+// lays out the tree below 'root' in a 'size' large area, a root Window is
+// the area itself, so its own Width and Height are not looked at
+pub fn layout(root: &UIElementRef, size: Size, m: &dyn TextMeasurer) -> LayoutResult {
+    let mut node = snapshot(root);
+    if root.lock().get_ui_type_name() == "Window" {
+        node.layout.width = None;
+        node.layout.height = None;
+        node.layout.min_width = 0.0;
+        node.layout.min_height = 0.0;
+        node.layout.max_width = f64::INFINITY;
+        node.layout.max_height = f64::INFINITY;
+        node.layout.margin = Thickness::uniform(0.0);
+        node.layout.horizontal_alignment = HorizontalAlignment::Stretch;
+        node.layout.vertical_alignment = VerticalAlignment::Stretch;
+    }
+
+    measure(&mut node, size, m);
+    let mut result = LayoutResult {
+        rects: Vec::new(),
+        grids: Vec::new(),
+    };
+    arrange(&node, Rect::new(0.0, 0.0, size.width, size.height), &mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::{layout, wrap_text, FixedWidthMeasurer, Rect, Size};
+    use crate::ui_elements::UITree;
+    use crate::xaml_reader;
+
+    fn rect_of(t: &UITree, size: Size, name: &str) -> Rect {
+        let root = t.root.as_ref().unwrap();
+        let result = layout(root, size, &FixedWidthMeasurer);
+        let id = t.find_by_name(name).unwrap().lock().get_id();
+        result.get(&id).unwrap()
+    }
+
+    #[test]
+    fn stack_panel_stacks_children() {
+        let text = "<Window><StackPanel Spacing=\"5\">\
+                    <TextBlock Name=\"A\" FontSize=\"10\" Text=\"ab\"/>\
+                    <TextBlock Name=\"B\" FontSize=\"10\" Text=\"abcd\" HorizontalAlignment=\"Right\"/>\
+                    <TextBlock Name=\"C\" FontSize=\"10\" Text=\"x\" Margin=\"4,2\"/>\
+                    </StackPanel></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let size = Size::new(200.0, 100.0);
+        assert_eq!(rect_of(&t, size, "A"), Rect::new(0.0, 0.0, 200.0, 12.0));
+        assert_eq!(rect_of(&t, size, "B"), Rect::new(176.0, 17.0, 24.0, 12.0));
+        assert_eq!(rect_of(&t, size, "C"), Rect::new(4.0, 36.0, 192.0, 12.0));
+    }
+
+    #[test]
+    fn grid_sizes_tracks() {
+        let text = "<Window><Grid>\
+                    <Grid.ColumnDefinitions><ColumnDefinition Width=\"100\"/><ColumnDefinition Width=\"*\"/></Grid.ColumnDefinitions>\
+                    <Grid.RowDefinitions><RowDefinition Height=\"Auto\"/><RowDefinition Height=\"*\"/></Grid.RowDefinitions>\
+                    <TextBlock Name=\"Head\" FontSize=\"10\" Text=\"x\"/>\
+                    <TextBlock Name=\"Cell\" Grid.Row=\"1\" Grid.Column=\"1\" Margin=\"5\" Text=\"y\"/>\
+                    <TextBlock Name=\"Wide\" Grid.Row=\"1\" Grid.ColumnSpan=\"2\" Width=\"50\" Text=\"z\"/>\
+                    </Grid></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let size = Size::new(300.0, 100.0);
+        assert_eq!(rect_of(&t, size, "Head"), Rect::new(0.0, 0.0, 100.0, 12.0));
+        assert_eq!(rect_of(&t, size, "Cell"), Rect::new(105.0, 17.0, 190.0, 78.0));
+        assert_eq!(rect_of(&t, size, "Wide"), Rect::new(125.0, 12.0, 50.0, 88.0));
+    }
+
+    #[test]
+    fn text_is_wrapped() {
+        assert_eq!(wrap_text("aa bb cc", Some(5)), vec!["aa bb", "cc"]);
+        assert_eq!(wrap_text("abcdefg", Some(3)), vec!["abc", "def", "g"]);
+        assert_eq!(wrap_text("a b", None), vec!["a b"]);
+    }
+}
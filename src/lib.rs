@@ -1,59 +1,397 @@
+use std::{fs::File, io::BufReader, path::Path};
+
 use crate::syn::ImplItem::Fn;
 use proc_macro::TokenStream;
 use quote::{format_ident, quote, ToTokens, TokenStreamExt};
-use syn::{self, parse_macro_input, ItemImpl};
+use syn::{self, parse_macro_input, spanned::Spanned, FnArg, Item, ItemImpl, ItemStruct, LitStr};
+use xml::common::Position;
+use xml::reader::{EventReader, XmlEvent};
+
+#[path = "routed_events.rs"]
+mod routed_events;
+
+// whether the attribute 'name' gives the handler of a routed event, as
+// 'Event' or as 'Owner.Event'
+fn is_event_attribute(name: &str) -> bool {
+    let event = |n: &str| n.rsplit('.').next().unwrap_or(n).to_string();
+    routed_events::ROUTED_EVENTS.iter().any(|e| event(e) == event(name))
+}
+
+// handler names in XAML match method names ignoring case and underscores,
+// 'Button_Click' calls 'button_click'
+fn normalize_handler_name(name: &str) -> String {
+    name.replace('_', "").to_lowercase()
+}
+
+// a handler named by a document
+struct HandlerRef {
+    name: String,
+    attribute: String,
+    line: u64,
+    column: u64,
+}
+
+fn read_handler_refs(path: &Path) -> Result<Vec<HandlerRef>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut parser = EventReader::new(BufReader::new(file));
+    let mut refs = Vec::new();
+    loop {
+        match parser.next() {
+            Ok(XmlEvent::StartElement { attributes, .. }) => {
+                let pos = parser.position();
+                for a in attributes.iter() {
+                    let local = &a.name.local_name;
+                    if is_event_attribute(local) {
+                        refs.push(HandlerRef {
+                            name: a.value.to_string(),
+                            attribute: local.to_string(),
+                            line: pos.row + 1,
+                            column: pos.column + 1,
+                        });
+                    }
+                }
+            }
+            Ok(XmlEvent::EndDocument) => break,
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+            _ => {}
+        }
+    }
+    Ok(refs)
+}
+
+// checks the handler names of the document 'xaml' against the handlers of
+// the impl block, errors point at the attribute argument
+fn check_handlers(xaml: &LitStr, funcs: &[FoundFuncs]) -> syn::Result<proc_macro2::TokenStream> {
+    let dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let path = Path::new(&dir).join(xaml.value());
+    let refs = read_handler_refs(&path).map_err(|e| syn::Error::new(xaml.span(), e))?;
+
+    let known: Vec<String> = funcs.iter().map(|f| f.name.to_string()).collect();
+    let mut errors: Option<syn::Error> = None;
+    for r in refs.iter() {
+        let n = normalize_handler_name(&r.name);
+        if known.iter().any(|k| normalize_handler_name(k) == n) {
+            continue;
+        }
+        let e = syn::Error::new(
+            xaml.span(),
+            format!(
+                "no handler for {}=\"{}\" at {}:{}:{}, the handlers are: {}",
+                r.attribute,
+                r.name,
+                xaml.value(),
+                r.line,
+                r.column,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ),
+        );
+        match &mut errors {
+            Some(all) => all.combine(e),
+            None => errors = Some(e),
+        }
+    }
+    if let Some(e) = errors {
+        return Err(e);
+    }
+
+    // rebuilds when the document changes
+    let path = path.to_string_lossy().to_string();
+    Ok(quote! {
+        const _: &[u8] = include_bytes!(#path);
+    })
+}
 
 struct FoundFuncs {
     name: String,
-    //inputs : Punctuated<FnArg, Comma>
+    // takes '&mut self'
+    mutable: bool,
+    // takes '(sender: &UIElementRef, args: &RoutedEventArgs)'
+    with_args: bool,
 }
 
 impl ToTokens for FoundFuncs {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let name = &self.name;
         let ident = format_ident!("{}", self.name);
+        let func = match (self.mutable, self.with_args) {
+            (false, false) => quote! { MethodFunc::Plain(SelfType::#ident) },
+            (false, true) => quote! { MethodFunc::WithArgs(SelfType::#ident) },
+            (true, false) => quote! { MethodFunc::PlainMut(SelfType::#ident) },
+            (true, true) => quote! { MethodFunc::WithArgsMut(SelfType::#ident) },
+        };
         let toks = quote! {
-            MethodInfo{ name: #name.to_string(), func: SelfType::#ident}
+            MethodInfo{ name: #name.to_string(), func: #func}
         };
 
         tokens.append_all(toks);
     }
 }
 
-fn get_name_from_impl_block(input: &ItemImpl) -> syn::Ident {
-    let impl_path = &input.self_ty;
-    let impl_ref = impl_path.as_ref();
-    match impl_ref {
-        syn::Type::Path(p) => {
-            let segments = &p.path.segments;
-            let name = segments[0].ident.to_string();
-            let impl_name = name;
-            let name = format_ident!("{}", impl_name);
-            name
+// a property of the code-behind, backed by a field or by a getter with an
+// optional 'set_' setter
+struct FoundProperty {
+    // the name bindings use, the PascalCase spelling unless given
+    name: String,
+    // the field or getter
+    ident: syn::Ident,
+    // the type of a field
+    ty: Option<syn::Type>,
+    writable: bool,
+}
+
+impl FoundProperty {
+    // the field or getter name and the property name both match
+    fn pattern(&self) -> proc_macro2::TokenStream {
+        let name = &self.name;
+        let rust_name = self.ident.to_string();
+        if *name == rust_name {
+            quote! { #name }
+        } else {
+            quote! { #name | #rust_name }
         }
+    }
+}
 
-        _ => {
-            panic!("unhandled assoc type: {}", impl_path.to_token_stream());
+// whether 'arg' is a '&T' whose type path ends in 'name'
+fn is_ref_to(arg: &FnArg, name: &str) -> bool {
+    let FnArg::Typed(t) = arg else {
+        return false;
+    };
+    match t.ty.as_ref() {
+        syn::Type::Reference(r) => match r.elem.as_ref() {
+            syn::Type::Path(p) => p.path.segments.last().map(|s| s.ident == name).unwrap_or(false),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn pascal_case(s: &str) -> String {
+    s.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+// removes the #[property] attributes, returns the property they declare
+// when there is one. Accepts #[property], #[property(readonly)] and
+// #[property(name = "...")]
+fn take_property_attribute(attrs: &mut Vec<syn::Attribute>, ident: &syn::Ident) -> syn::Result<Option<FoundProperty>> {
+    let mut found = None;
+    let mut error = None;
+    attrs.retain(|a| {
+        if !a.path().is_ident("property") {
+            return true;
+        }
+        let mut p = FoundProperty {
+            name: pascal_case(&ident.to_string()),
+            ident: ident.clone(),
+            ty: None,
+            writable: true,
+        };
+        if let syn::Meta::List(_) = a.meta {
+            let parsed = a.parse_nested_meta(|meta| {
+                if meta.path.is_ident("readonly") {
+                    p.writable = false;
+                    Ok(())
+                } else if meta.path.is_ident("name") {
+                    p.name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("expected 'readonly' or 'name = \"...\"'"))
+                }
+            });
+            if let Err(e) = parsed {
+                error = Some(e);
+            }
         }
+        found = Some(p);
+        false
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(found),
     }
 }
 
+fn get_name_from_impl_block(input: &ItemImpl) -> syn::Result<syn::Ident> {
+    match input.self_ty.as_ref() {
+        // spanned at the call site, so lints skip the generated MethodInfo_X types
+        syn::Type::Path(p) if p.qself.is_none() => {
+            let last = &p.path.segments[p.path.segments.len() - 1];
+            Ok(format_ident!("{}", last.ident.to_string()))
+        }
+        other => Err(syn::Error::new(
+            other.span(),
+            format!("#[inspectable] can't implement '{}', it needs a struct name", other.to_token_stream()),
+        )),
+    }
+}
+
+// the code-behind of a XAML document: '#[inspectable]' on its impl block
+// collects the event handlers and '#[property]' getters, and declares the
+// struct with the tree. A struct of its own is optional: marked
+// '#[inspectable]', its fields marked '#[property]' can be bound to, and the
+// impl block is marked '#[inspectable(fields)]' to use it. The fields need to
+// implement Default, writable properties also PartialEq
 #[proc_macro_attribute]
-pub fn inspectable(_meta: TokenStream, code: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(code as ItemImpl);
+pub fn inspectable(meta: TokenStream, code: TokenStream) -> TokenStream {
+    // #[inspectable(xaml = "ui/main.xaml")] on the impl block checks the
+    // handler names the document gives for the events of routed_events.rs,
+    // the path is relative to the crate root
+    let mut xaml: Option<LitStr> = None;
+    let mut fields: Option<syn::Path> = None;
+    let args = syn::meta::parser(|meta| {
+        if meta.path.is_ident("xaml") {
+            xaml = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("fields") {
+            fields = Some(meta.path);
+            Ok(())
+        } else {
+            Err(meta.error("expected 'fields' or 'xaml = \"...\"'"))
+        }
+    });
+    parse_macro_input!(meta with args);
+    let input = parse_macro_input!(code as Item);
+
+    let expanded = match input {
+        Item::Struct(_) if xaml.is_some() => Err(syn::Error::new(
+            xaml.unwrap().span(),
+            "'xaml' goes on the impl block of the code-behind",
+        )),
+        Item::Struct(_) if fields.is_some() => Err(syn::Error::new(
+            fields.unwrap().span(),
+            "'fields' goes on the impl block of the code-behind",
+        )),
+        Item::Struct(s) => inspectable_struct(s),
+        Item::Impl(i) => inspectable_impl(i, xaml, fields.is_some()),
+        other => Err(syn::Error::new(other.span(), "#[inspectable] goes on the struct and the impl block of the code-behind")),
+    };
+    match expanded {
+        Ok(t) => TokenStream::from(t),
+        Err(e) => TokenStream::from(e.to_compile_error()),
+    }
+}
+
+fn inspectable_struct(mut input: ItemStruct) -> syn::Result<proc_macro2::TokenStream> {
+    let name = input.ident.clone();
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "the code-behind can't be generic"));
+    }
+
+    let mut fields = Vec::new();
+    let mut props = Vec::<FoundProperty>::new();
+    match &mut input.fields {
+        syn::Fields::Named(named) => {
+            for f in named.named.iter_mut() {
+                let ident = f.ident.clone().unwrap();
+                if let Some(mut p) = take_property_attribute(&mut f.attrs, &ident)? {
+                    p.ty = Some(f.ty.clone());
+                    props.push(p);
+                }
+                fields.push(ident);
+            }
+        }
+        syn::Fields::Unit => {}
+        syn::Fields::Unnamed(_) => {
+            return Err(syn::Error::new(input.fields.span(), "the code-behind needs named fields"));
+        }
+    }
+
+    let user_fields = match &input.fields {
+        syn::Fields::Named(named) => {
+            let f = named.named.iter();
+            quote! { #(#f,)* }
+        }
+        _ => quote! {},
+    };
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let method_info = format_ident!("MethodInfo_{}", name);
+
+    let getters = props.iter().map(|p| {
+        let pattern = p.pattern();
+        let ident = &p.ident;
+        quote! {
+            #pattern => Some(crate::properties::ToPropertyValue::to_property_value(&self.#ident)),
+        }
+    });
+    let setters = props.iter().filter(|p| p.writable).map(|p| {
+        let pattern = p.pattern();
+        let ident = &p.ident;
+        let ty = &p.ty;
+        let prop_name = &p.name;
+        quote! {
+            #pattern => match <#ty as crate::properties::FromPropertyValue>::from_property_value(&value) {
+                Some(v) => {
+                    if self.#ident != v {
+                        self.#ident = v;
+                        crate::callable::CallableByName::property_changed(self, #prop_name);
+                    }
+                    true
+                }
+                None => false,
+            },
+        }
+    });
 
-    //println!(" PARSED MACRO:{:#?}", input);
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name {
+            #user_fields
+            tree: Option<crate::ui_elements::UITreeRef>,
+            info: Vec<#method_info>,
+            property_handlers: Vec<crate::properties::PropertyChangedHandler>,
+        }
+
+        impl #name {
+            pub fn new() -> #name {
+                #name {
+                    #(#fields: Default::default(),)*
+                    tree: Option::None,
+                    info: #name::methods(),
+                    property_handlers: Vec::new(),
+                }
+            }
+
+            fn get_field_property(&self, name: &str) -> Option<crate::properties::PropertyValue> {
+                match name {
+                    #(#getters)*
+                    _ => None,
+                }
+            }
+
+            fn set_field_property(&mut self, name: &str, value: crate::properties::PropertyValue) -> bool {
+                match name {
+                    #(#setters)*
+                    _ => false,
+                }
+            }
+        }
+    })
+}
 
+fn inspectable_impl(mut input: ItemImpl, xaml: Option<LitStr>, fields: bool) -> syn::Result<proc_macro2::TokenStream> {
     let mut funcs = Vec::<FoundFuncs>::new();
+    let mut props = Vec::<FoundProperty>::new();
 
-    let name = get_name_from_impl_block(&input);
+    let name = get_name_from_impl_block(&input)?;
 
-    for item in &input.items {
+    for item in input.items.iter_mut() {
         if let Fn(impl_item) = item {
-            // println!("found impl item !!!!!!!!!!!!!!!!!!!!!!!!!!!!!!! {:#?}", impl_item);
+            let ident = impl_item.sig.ident.clone();
+            if let Some(p) = take_property_attribute(&mut impl_item.attrs, &ident)? {
+                props.push(p);
+                continue;
+            }
 
             let sig = &impl_item.sig;
-            let ident = &sig.ident;
             let generics = &sig.generics;
             let inputs = &sig.inputs;
 
@@ -63,43 +401,144 @@ pub fn inspectable(_meta: TokenStream, code: TokenStream) -> TokenStream {
                 continue;
             }
 
-            if inputs.len() != 1 {
+            // handlers take '&self' or '&mut self', optionally the sender and
+            // the event arguments, and return nothing
+            let mutable = match inputs.first() {
+                Some(FnArg::Receiver(r)) if r.reference.is_some() => r.mutability.is_some(),
+                _ => continue,
+            };
+            if sig.output != syn::ReturnType::Default {
+                continue;
+            }
+            let with_args = inputs.len() == 3
+                && is_ref_to(&inputs[1], "UIElementRef")
+                && is_ref_to(&inputs[2], "RoutedEventArgs");
+            if inputs.len() != 1 && !with_args {
                 continue;
             }
 
+            if let Some(f) = funcs.iter().find(|f| normalize_handler_name(&f.name) == normalize_handler_name(&ident.to_string())) {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    format!("handler '{}' can't be told apart from '{}' by XAML", ident, f.name),
+                ));
+            }
             funcs.push(FoundFuncs {
                 name: ident.to_string(),
-                //inputs: inputs.clone()
+                mutable,
+                with_args,
             });
         }
     }
 
+    // a getter is writable when there is a 'set_' method next to it
+    let setter_names: Vec<String> = input
+        .items
+        .iter()
+        .filter_map(|i| match i {
+            Fn(f) => Some(f.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    for p in props.iter_mut() {
+        p.writable = p.writable && setter_names.contains(&format!("set_{}", p.ident));
+    }
+
+    let getters = props.iter().map(|p| {
+        let pattern = p.pattern();
+        let ident = &p.ident;
+        quote! {
+            #pattern => Some(crate::properties::ToPropertyValue::to_property_value(&self.#ident())),
+        }
+    });
+    let setters = props.iter().filter(|p| p.writable).map(|p| {
+        let pattern = p.pattern();
+        let setter = format_ident!("set_{}", p.ident);
+        let prop_name = &p.name;
+        quote! {
+            #pattern => match crate::properties::FromPropertyValue::from_property_value(&value) {
+                Some(v) => {
+                    self.#setter(v);
+                    crate::callable::CallableByName::property_changed(self, #prop_name);
+                    true
+                }
+                None => false,
+            },
+        }
+    });
+
+    // the impl is generated anyway, so the unknown handlers are the only errors
+    let checked = match xaml.map(|x| check_handlers(&x, &funcs)) {
+        Some(Ok(t)) => t,
+        Some(Err(e)) => e.to_compile_error(),
+        None => quote! {},
+    };
+
     let method_info = format_ident!("MethodInfo_{}", name);
+    let method_func = format_ident!("MethodFunc_{}", name);
+
+    // without a '#[inspectable]' struct the code-behind only holds the tree
+    let declared = if fields {
+        quote! {}
+    } else {
+        quote! {
+            struct #name {
+                tree: Option<crate::ui_elements::UITreeRef>,
+                info: Vec<#method_info>,
+                property_handlers: Vec<crate::properties::PropertyChangedHandler>,
+            }
+
+            impl #name {
+                pub fn new() -> #name {
+                    #name {
+                        tree: Option::None,
+                        info: #name::methods(),
+                        property_handlers: Vec::new(),
+                    }
+                }
+
+                fn get_field_property(&self, _name: &str) -> Option<crate::properties::PropertyValue> {
+                    None
+                }
+
+                fn set_field_property(&mut self, _name: &str, _value: crate::properties::PropertyValue) -> bool {
+                    false
+                }
+            }
+        }
+    };
+
     let mut expanded = quote! {
+        #checked
+        #declared
+
         // The generated impl.
+        #[derive(Clone, Copy)]
+        enum #method_func {
+            Plain(fn(&#name)),
+            WithArgs(fn(&#name, &crate::ui_elements::UIElementRef, &crate::events::RoutedEventArgs)),
+            PlainMut(fn(&mut #name)),
+            WithArgsMut(fn(&mut #name, &crate::ui_elements::UIElementRef, &crate::events::RoutedEventArgs)),
+        }
+
         struct #method_info {
             name: String,
-            func: fn(&#name)
-        }
-        struct #name {
-            tree: Option<UITreeRef>,
-            info: Vec<#method_info>
+            func: #method_func
         }
 
         impl #name {
-            pub fn new() -> #name {
+            fn methods() -> Vec<#method_info> {
                 type MethodInfo = #method_info;
+                type MethodFunc = #method_func;
                 type SelfType = #name;
-                #name {
-                    tree: Option::None,
-                    info: vec![#(#funcs),*]
-                }
+                vec![#(#funcs),*]
             }
 
+            // ignores case and underscores, like the check of #[inspectable(xaml = ...)]
             fn find_method(&self, name: &str) -> Option<&#method_info> {
+                let wanted = name.replace('_', "").to_lowercase();
                 for it in self.info.iter() {
-                    //println!("examine: {} vs {}", it.name, name);
-                    if it.name.to_lowercase() == name.to_lowercase() {
+                    if it.name.replace('_', "").to_lowercase() == wanted {
                         return Option::Some(it);
                     }
                 }
@@ -107,29 +546,58 @@ pub fn inspectable(_meta: TokenStream, code: TokenStream) -> TokenStream {
             }
         }
 
-        use callable::CallableByName;
-
-        impl CallableByName for #name {
+        impl crate::callable::CallableByName for #name {
 
-            fn get_tree(&self) -> Option<UITreeRef> {
+            fn get_tree(&self) -> Option<crate::ui_elements::UITreeRef> {
                 return self.tree;
             }
 
-            fn set_tree(&mut self, tree: Option<UITreeRef>)
+            fn set_tree(&mut self, tree: Option<crate::ui_elements::UITreeRef>)
             {
                 self.tree = tree;
             }
 
-            fn call_method(&self, name: &str) {
-                let opt = self.find_method(name);
-                match (opt) {
-                    Some(m) => {
-                        let func = m.func;
-                        func(self);
+            fn call_method(&mut self, name: &str, sender: &crate::ui_elements::UIElementRef, args: &crate::events::RoutedEventArgs) {
+                let opt = self.find_method(name).map(|m| (m.name.to_string(), m.func));
+                match opt {
+                    Some((method, func)) => {
+                        ::log::debug!(target: "dispatch", "calling {}::{}", stringify!(#name), method);
+                        match func {
+                            #method_func::Plain(func) => func(self),
+                            #method_func::WithArgs(func) => func(self, sender, args),
+                            #method_func::PlainMut(func) => func(self),
+                            #method_func::WithArgsMut(func) => func(self, sender, args),
+                        }
                     }
 
                     None => {
-                        println!("failed to find method: {}", name);
+                        ::log::warn!(target: "dispatch", "failed to find method: {}", name);
+                    }
+                }
+            }
+
+            fn get_property(&self, name: &str) -> Option<crate::properties::PropertyValue> {
+                match name {
+                    #(#getters)*
+                    _ => self.get_field_property(name),
+                }
+            }
+
+            fn set_property(&mut self, name: &str, value: crate::properties::PropertyValue) -> bool {
+                match name {
+                    #(#setters)*
+                    _ => self.set_field_property(name, value),
+                }
+            }
+
+            fn add_property_changed_handler(&mut self, handler: crate::properties::PropertyChangedHandler) {
+                self.property_handlers.push(handler);
+            }
+
+            fn property_changed(&self, name: &str) {
+                if let Some(v) = crate::callable::CallableByName::get_property(self, name) {
+                    for h in self.property_handlers.iter() {
+                        h(name, &v);
                     }
                 }
             }
@@ -137,11 +605,7 @@ pub fn inspectable(_meta: TokenStream, code: TokenStream) -> TokenStream {
     };
 
     let new_toks = quote!(#input);
-
-    // TokenStream::from(expanded.append_all(new_toks))
     expanded.append_all(new_toks);
 
-    // println!("expanded = {}", expanded);
-
-    TokenStream::from(expanded)
+    Ok(expanded)
 }
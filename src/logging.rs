@@ -0,0 +1,35 @@
+use std::env;
+
+use log::LevelFilter;
+
+// diagnostics go through the log crate under one target per subsystem,
+// nothing is printed unless enabled with RAMAUI_LOG or --log, using the
+// env_logger syntax: 'debug', 'events=trace', 'parser=debug,dispatch=warn'
+
+pub const LOG_ENV: &str = "RAMAUI_LOG";
+
+// reading XAML documents
+pub const PARSER: &str = "parser";
+// creating the GTK widgets
+pub const BUILDER: &str = "builder";
+// events travelling through the element tree
+pub const EVENTS: &str = "events";
+// events arriving from a backend and calls into the code-behind
+pub const DISPATCH: &str = "dispatch";
+// bindings between element properties and the data context
+pub const BINDING: &str = "binding";
+
+// 'spec' from the command line is applied after the environment variable,
+// so it wins where both set a target
+pub fn init(spec: Option<&str>) {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Off);
+    if let Ok(s) = env::var(LOG_ENV) {
+        builder.parse_filters(&s);
+    }
+    if let Some(s) = spec {
+        builder.parse_filters(s);
+    }
+    // a logger set up earlier, by tests or an embedding application, stays
+    let _ = builder.try_init();
+}
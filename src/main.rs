@@ -1,37 +1,54 @@
 use std::env;
 
+mod binding;
 mod button;
+mod cli;
 mod content_page;
+mod converters;
+mod element_registry;
 mod grid_layout;
+mod html_export;
+mod json_dump;
 mod label;
+mod layout;
+mod logging;
+mod markup_extension;
+mod namespaces;
+mod properties;
+mod raster;
+mod render;
+mod routed_events;
 mod stack_layout;
 mod text_block;
+mod tui;
 mod ui_elements;
 mod unknown_ui_elt;
 mod visitor;
 mod window;
+mod xaml_error;
+mod xaml_format;
 mod xaml_reader;
+mod xaml_writer;
 mod ui_builder;
 mod callable;
 mod events;
+use callable::CallableByName;
+use cli::{Command, ExportFormat};
 use parking_lot::Mutex;
 use ramaui::inspectable;
 use ui_builder::start_interpreter;
-use ui_elements::{UIElementRef, UITree, UITreeRef};
+use ui_elements::{UITree, UITreeRef};
 
 
-fn usage() {
-    let args: Vec<String> = env::args().collect();
-    println!("USAGE: <xaml>");
-    for s in args {
-        println!("saw arg: {}", s);
-    }
+fn usage(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("{}", cli::USAGE);
     std::process::exit(1);
 }
 
 
 
-#[inspectable(bar)]
+#[inspectable]
 impl MainWindow {
     pub fn button_click(&self) {
         println!("called MainWindow::button click!");
@@ -41,39 +58,124 @@ impl MainWindow {
     }
 }
 
-fn create_tree(root_elt_ref: UIElementRef) -> Option<UITreeRef> {
-    let mut tree = UITree::new();
-    tree.root = Some(root_elt_ref.clone());
+fn create_tree(tree: UITree) -> Option<UITreeRef> {
     let r = Box::leak(Box::new(tree));
-    return Some(r);
+    Some(r)
 }
 
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        usage();
+fn read_or_exit(filename: &String, strict: bool) -> UITree {
+    let mut reader = xaml_reader::XamlReader::new();
+    reader.set_strict(strict);
+    match reader.read_file(filename) {
+        Ok(t) => t,
+        Err(err) => {
+            eprintln!("failed to read xml: {}", err);
+            std::process::exit(1);
+        }
     }
+}
 
-    let filename = &args[1];
-
-    let root_elt_ref = xaml_reader::read_xaml(filename);
-    match root_elt_ref {
-        Result::Ok(t) => {
-            t.lock().dump(0);
-
-            let win = Box::leak::<'static>(Box::new(Mutex::new(MainWindow::new())));
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    match cli::take_log_spec(&mut args) {
+        Ok(spec) => logging::init(spec.as_deref()),
+        Err(msg) => usage(&msg),
+    }
+    let strict = cli::take_flag(&mut args, "--strict");
+    let command = match cli::parse_args(&args) {
+        Ok(c) => c,
+        Err(msg) => usage(&msg),
+    };
 
-            {
-                let mut r = win.lock();
-                let tree = create_tree(t);
-                r.set_tree(tree);
+    let filename = match command {
+        Command::Run { file } => file,
+        Command::Render { file, output, size } => {
+            let tree = read_or_exit(&file, strict);
+            if let Err(err) = render::render_to_file(&tree, &output, size) {
+                eprintln!("failed to render: {}", err);
+                std::process::exit(1);
             }
-
-            start_interpreter(win);
+            return;
         }
-        Result::Err(err) => {
-            println!("failed to read xml: {}", err)
+        Command::Export { file, format, output } => {
+            let tree = read_or_exit(&file, strict);
+            let result = match (format, output) {
+                (ExportFormat::Html, output) => html_export::export_html(&tree, output.as_deref()),
+                (ExportFormat::Xaml, Some(path)) => xaml_writer::save_xaml(&tree, &path),
+                (ExportFormat::Xaml, None) => xaml_writer::write_xaml(&tree).map(|text| print!("{}", text)),
+            };
+            if let Err(err) = result {
+                eprintln!("failed to export: {}", err);
+                std::process::exit(1);
+            }
+            return;
         }
+        Command::Dump { file, json } => {
+            let tree = read_or_exit(&file, strict);
+            if !json {
+                if let Some(root) = &tree.root {
+                    root.lock().dump(0);
+                }
+                return;
+            }
+            match json_dump::dump_json(&tree) {
+                Ok(text) => print!("{}", text),
+                Err(err) => {
+                    eprintln!("failed to dump: {}", err);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Fmt {
+            files,
+            check,
+            width,
+        } => {
+            let mut failed = false;
+            for f in files.iter() {
+                match xaml_format::format_file(f, width, check) {
+                    Ok(true) => {}
+                    Ok(false) if check => {
+                        eprintln!("{}: not formatted", f);
+                        failed = true;
+                    }
+                    Ok(false) => println!("formatted {}", f),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Tui { file } => {
+            let tree = read_or_exit(&file, strict);
+            let win = Box::leak::<'static>(Box::new(Mutex::new(MainWindow::new())));
+            win.lock().set_tree(create_tree(tree));
+            binding::activate(win);
+            if let Err(err) = tui::run_tui(win) {
+                eprintln!("terminal error: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
+
+    // 'ramaui dump' prints the parsed tree
+    let t = read_or_exit(&filename, strict);
+    let win = Box::leak::<'static>(Box::new(Mutex::new(MainWindow::new())));
+
+    {
+        let mut r = win.lock();
+        let tree = create_tree(t);
+        r.set_tree(tree);
     }
+    binding::activate(win);
+
+    start_interpreter(win);
 }
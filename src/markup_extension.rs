@@ -0,0 +1,210 @@
+// attribute values in braces are markup extensions: '{Binding Path=Name,
+// Mode=TwoWay}' is the extension 'Binding' with named arguments, '{Binding
+// Name}' passes 'Name' positionally. A value starting with '{}' is a literal
+// whose text follows the '{}'
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkupExtension {
+    pub name: String,
+    pub positional: Vec<String>,
+    pub named: Vec<(String, String)>,
+}
+
+pub fn is_markup_extension(value: &str) -> bool {
+    value.starts_with('{') && !value.starts_with("{}")
+}
+
+// the text of a literal attribute value
+pub fn unescape_literal(value: &str) -> &str {
+    value.strip_prefix("{}").unwrap_or(value)
+}
+
+// the attribute spelling of a literal that could be read as an extension
+#[allow(dead_code)]
+pub fn escape_literal(value: &str) -> String {
+    if value.starts_with('{') {
+        format!("{{}}{}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+// quotes an argument value that would otherwise not be read back as one
+pub fn quote_argument(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && !value.contains([',', '=', '{', '}', '\'', '"', '\\']);
+    if plain {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+// splits the arguments at the commas outside of quotes and nested braces,
+// with the position of the '=' of a named argument
+fn split_arguments(text: &str) -> Result<Vec<(String, Option<usize>)>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut equals = None;
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(n) => {
+                    current.push(c);
+                    current.push(n);
+                }
+                None => return Err("'\\' at the end of the markup extension".to_string()),
+            },
+            q if quote == Some(q) => {
+                quote = None;
+                current.push(c);
+            }
+            _ if quote.is_some() => current.push(c),
+            '\'' | '"' if depth == 0 => {
+                quote = Some(c);
+                current.push(c);
+            }
+            '{' => {
+                depth += 1;
+                current.push(c);
+            }
+            '}' if depth == 0 => return Err("unbalanced '}' in the markup extension".to_string()),
+            '}' => {
+                depth -= 1;
+                current.push(c);
+            }
+            '=' if depth == 0 && equals.is_none() => {
+                equals = Some(current.len());
+                current.push(c);
+            }
+            ',' if depth == 0 => {
+                args.push((current, equals));
+                current = String::new();
+                equals = None;
+            }
+            _ => current.push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quote in the markup extension".to_string());
+    }
+    if depth > 0 {
+        return Err("unbalanced '{' in the markup extension".to_string());
+    }
+    args.push((current, equals));
+    Ok(args)
+}
+
+// removes the quotes and '\' escapes, nested extensions are kept as written
+fn argument_value(raw: &str) -> String {
+    let v = raw.trim();
+    if v.starts_with('{') {
+        return v.to_string();
+    }
+    let quoted = v.len() >= 2 && (v.starts_with('\'') && v.ends_with('\'') || v.starts_with('"') && v.ends_with('"'));
+    let v = if quoted { &v[1..v.len() - 1] } else { v };
+
+    let mut ret = String::new();
+    let mut chars = v.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => ret.extend(chars.next()),
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+impl MarkupExtension {
+    pub fn parse(value: &str) -> Result<MarkupExtension, String> {
+        let inner = value
+            .trim()
+            .strip_prefix('{')
+            .and_then(|v| v.strip_suffix('}'))
+            .ok_or_else(|| "a markup extension is enclosed in '{' and '}'".to_string())?
+            .trim();
+        let (name, rest) = match inner.find(char::is_whitespace) {
+            Some(i) => (&inner[..i], inner[i..].trim()),
+            None => (inner, ""),
+        };
+        if name.is_empty() || name.contains(['{', '}', ',', '=']) {
+            return Err(format!("'{}' is not the name of a markup extension", name));
+        }
+
+        let mut ext = MarkupExtension {
+            name: name.to_string(),
+            positional: Vec::new(),
+            named: Vec::new(),
+        };
+        if rest.is_empty() {
+            return Ok(ext);
+        }
+        for (raw, equals) in split_arguments(rest)? {
+            if raw.trim().is_empty() {
+                return Err(format!("empty argument in {{{}}}", name));
+            }
+            match equals {
+                Some(i) => {
+                    let key = raw[..i].trim();
+                    if key.is_empty() {
+                        return Err(format!("argument without a name in {{{}}}", name));
+                    }
+                    ext.named.push((key.to_string(), argument_value(&raw[i + 1..])));
+                }
+                None if ext.named.is_empty() => ext.positional.push(argument_value(&raw)),
+                None => return Err(format!("positional argument after named ones in {{{}}}", name)),
+            }
+        }
+        Ok(ext)
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.named.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markup_extension::{escape_literal, is_markup_extension, quote_argument, unescape_literal, MarkupExtension};
+
+    #[test]
+    fn parses_markup_extensions() {
+        let ext = MarkupExtension::parse("{Binding}").unwrap();
+        assert_eq!(ext.name, "Binding");
+        assert!(ext.positional.is_empty() && ext.named.is_empty());
+
+        let ext = MarkupExtension::parse("{Binding UserName}").unwrap();
+        assert_eq!(ext.positional, vec!["UserName"]);
+
+        let ext = MarkupExtension::parse(" { Binding Path=User.Name ,Mode=TwoWay, FallbackValue='a, b' } ").unwrap();
+        assert_eq!(ext.get("Path"), Some("User.Name"));
+        assert_eq!(ext.get("Mode"), Some("TwoWay"));
+        assert_eq!(ext.get("FallbackValue"), Some("a, b"));
+
+        let ext = MarkupExtension::parse("{Binding Count, Converter={StaticResource C}, FallbackValue=it\\'s}").unwrap();
+        assert_eq!(ext.positional, vec!["Count"]);
+        assert_eq!(ext.get("Converter"), Some("{StaticResource C}"));
+        assert_eq!(ext.get("FallbackValue"), Some("it's"));
+
+        assert!(MarkupExtension::parse("{Binding").is_err());
+        assert!(MarkupExtension::parse("{Binding a,}").is_err());
+        assert!(MarkupExtension::parse("{Binding 'a}").is_err());
+        assert!(MarkupExtension::parse("{Binding Mode=OneWay, a}").is_err());
+        assert!(MarkupExtension::parse("{}").is_err());
+
+        assert!(is_markup_extension("{Binding}"));
+        assert!(!is_markup_extension("{}{Binding}"));
+        assert_eq!(unescape_literal("{}{Binding}"), "{Binding}");
+        assert_eq!(escape_literal("{Binding}"), "{}{Binding}");
+        assert_eq!(escape_literal("plain"), "plain");
+        assert_eq!(quote_argument("a, b"), "'a, b'");
+        assert_eq!(quote_argument("it's"), "'it\\'s'");
+        let ext = MarkupExtension::parse(&format!("{{X V={}}}", quote_argument("it's, {1}"))).unwrap();
+        assert_eq!(ext.get("V"), Some("it's, {1}"));
+    }
+}
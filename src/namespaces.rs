@@ -0,0 +1,72 @@
+// the default namespaces of WPF and MAUI documents, the built-in elements live here
+pub const PRESENTATION_NS: &str = "http://schemas.microsoft.com/winfx/2006/xaml/presentation";
+pub const MAUI_NS: &str = "http://schemas.microsoft.com/dotnet/2021/maui";
+
+// the XAML language namespace, conventionally bound to the 'x:' prefix
+pub const XAML_NS: &str = "http://schemas.microsoft.com/winfx/2006/xaml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XamlNamespace {
+    // no namespace or one of the presentation namespaces
+    Default,
+    // x:Class, x:Name and friends
+    Language,
+    // 'clr-namespace:Foo.Bar;assembly=Baz' and 'using:Foo.Bar' both map to 'Foo.Bar'
+    Code(String),
+    Other(String),
+}
+
+impl XamlNamespace {
+    pub fn resolve(uri: Option<&str>) -> XamlNamespace {
+        match uri {
+            None | Some("") | Some(PRESENTATION_NS) | Some(MAUI_NS) => XamlNamespace::Default,
+            Some(XAML_NS) => XamlNamespace::Language,
+            Some(u) => {
+                if let Some(rest) = u.strip_prefix("clr-namespace:") {
+                    let ns = rest.split(';').next().unwrap_or("");
+                    return XamlNamespace::Code(ns.trim().to_string());
+                }
+                if let Some(rest) = u.strip_prefix("using:") {
+                    return XamlNamespace::Code(rest.trim().to_string());
+                }
+                XamlNamespace::Other(u.to_string())
+            }
+        }
+    }
+
+    // the key element types are registered under in the ElementRegistry
+    pub fn registry_key(&self) -> Option<&str> {
+        match self {
+            XamlNamespace::Default | XamlNamespace::Language => None,
+            XamlNamespace::Code(ns) | XamlNamespace::Other(ns) => Some(ns.as_str()),
+        }
+    }
+}
+
+// xml-rs refuses documents with unbound prefixes, this recovers the prefix
+// from its "Element foo:Bar prefix is unbound" message
+pub fn unbound_prefix_from_message(msg: &str) -> Option<String> {
+    let name = msg.strip_suffix(" prefix is unbound")?;
+    let name = name
+        .strip_prefix("Element ")
+        .or_else(|| name.strip_prefix("Attribute "))?;
+    let qualified = match name.rfind('}') {
+        Some(i) => &name[i + 1..],
+        None => name,
+    };
+    qualified.split(':').next().map(|p| p.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::namespaces::unbound_prefix_from_message;
+
+    #[test]
+    fn reads_the_prefix_from_the_message() {
+        let prefix = |msg| unbound_prefix_from_message(msg);
+        assert_eq!(prefix("Element foo:Gauge prefix is unbound"), Some("foo".to_string()));
+        assert_eq!(prefix("Attribute b:Tag prefix is unbound"), Some("b".to_string()));
+        assert_eq!(prefix("Element {urn:x}c:Y prefix is unbound"), Some("c".to_string()));
+        assert_eq!(prefix("Unexpected end of stream"), None);
+    }
+}
@@ -0,0 +1,451 @@
+use std::fmt;
+
+use crate::converters::{
+    Color, GridLength, HorizontalAlignment, Thickness, VerticalAlignment, Visibility,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Int(i32),
+    Float(f64),
+    // a Width/Height style length left to the layout
+    Auto,
+    Bool(bool),
+    Str(String),
+    Thickness(Thickness),
+    Color(Color),
+    GridLength(GridLength),
+    // the canonical spelling of the enum member
+    Enum(String),
+}
+
+impl PropertyValue {
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            PropertyValue::Int(v) => Some(*v),
+            PropertyValue::Float(v) => Some(*v as i32),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            PropertyValue::Int(v) => Some(*v as f64),
+            PropertyValue::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PropertyValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_thickness(&self) -> Option<Thickness> {
+        match self {
+            PropertyValue::Thickness(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            PropertyValue::Color(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_grid_length(&self) -> Option<GridLength> {
+        match self {
+            PropertyValue::GridLength(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+// the XAML spelling of a value, parsing it again gives the same value
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Int(v) => write!(f, "{}", v),
+            PropertyValue::Float(v) => write!(f, "{}", v),
+            PropertyValue::Auto => f.write_str("Auto"),
+            PropertyValue::Bool(v) => f.write_str(if *v { "True" } else { "False" }),
+            PropertyValue::Str(v) | PropertyValue::Enum(v) => f.write_str(v),
+            PropertyValue::Thickness(v) => write!(f, "{}", v),
+            PropertyValue::Color(v) => write!(f, "{}", v),
+            PropertyValue::GridLength(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+// conversions between property values and the Rust types of code-behind
+// properties exposed by #[inspectable], text is parsed like an attribute
+#[allow(dead_code)]
+pub trait ToPropertyValue {
+    fn to_property_value(&self) -> PropertyValue;
+}
+
+#[allow(dead_code)]
+pub trait FromPropertyValue: Sized {
+    fn from_property_value(value: &PropertyValue) -> Option<Self>;
+}
+
+impl ToPropertyValue for PropertyValue {
+    fn to_property_value(&self) -> PropertyValue {
+        self.clone()
+    }
+}
+
+impl FromPropertyValue for PropertyValue {
+    fn from_property_value(value: &PropertyValue) -> Option<PropertyValue> {
+        Some(value.clone())
+    }
+}
+
+impl ToPropertyValue for String {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Str(self.to_string())
+    }
+}
+
+impl FromPropertyValue for String {
+    fn from_property_value(value: &PropertyValue) -> Option<String> {
+        Some(value.to_string())
+    }
+}
+
+impl ToPropertyValue for i32 {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Int(*self)
+    }
+}
+
+impl FromPropertyValue for i32 {
+    fn from_property_value(value: &PropertyValue) -> Option<i32> {
+        match value {
+            PropertyValue::Str(s) => PropertyKind::Int.parse(s).ok()?.as_i32(),
+            v => v.as_i32(),
+        }
+    }
+}
+
+impl ToPropertyValue for f64 {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Float(*self)
+    }
+}
+
+impl FromPropertyValue for f64 {
+    fn from_property_value(value: &PropertyValue) -> Option<f64> {
+        match value {
+            PropertyValue::Str(s) => PropertyKind::Float.parse(s).ok()?.as_f64(),
+            v => v.as_f64(),
+        }
+    }
+}
+
+impl ToPropertyValue for bool {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Bool(*self)
+    }
+}
+
+impl FromPropertyValue for bool {
+    fn from_property_value(value: &PropertyValue) -> Option<bool> {
+        match value {
+            PropertyValue::Str(s) => PropertyKind::Bool.parse(s).ok()?.as_bool(),
+            v => v.as_bool(),
+        }
+    }
+}
+
+impl ToPropertyValue for Thickness {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Thickness(*self)
+    }
+}
+
+impl FromPropertyValue for Thickness {
+    fn from_property_value(value: &PropertyValue) -> Option<Thickness> {
+        match value {
+            PropertyValue::Str(s) => Thickness::parse(s).ok(),
+            v => v.as_thickness(),
+        }
+    }
+}
+
+impl ToPropertyValue for Color {
+    fn to_property_value(&self) -> PropertyValue {
+        PropertyValue::Color(*self)
+    }
+}
+
+impl FromPropertyValue for Color {
+    fn from_property_value(value: &PropertyValue) -> Option<Color> {
+        match value {
+            PropertyValue::Str(s) => Color::parse(s).ok(),
+            v => v.as_color(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum PropertyKind {
+    Int,
+    Float,
+    // a Float that also accepts 'Auto'
+    Length,
+    Bool,
+    Str,
+    Thickness,
+    Color,
+    GridLength,
+    Enum(&'static [&'static str]),
+    // for values with their own syntax, such as FontWeight
+    Custom(fn(&str) -> Result<PropertyValue, String>),
+}
+
+impl PropertyKind {
+    pub fn parse(&self, s: &str) -> Result<PropertyValue, String> {
+        let v = s.trim();
+        match self {
+            PropertyKind::Int => v
+                .parse::<i32>()
+                .map(PropertyValue::Int)
+                .map_err(|_| format!("'{}' is not an integer", v)),
+            PropertyKind::Float => v
+                .parse::<f64>()
+                .map(PropertyValue::Float)
+                .map_err(|_| format!("'{}' is not a number", v)),
+            PropertyKind::Length => {
+                if v.eq_ignore_ascii_case("auto") {
+                    return Ok(PropertyValue::Auto);
+                }
+                v.parse::<f64>()
+                    .map(PropertyValue::Float)
+                    .map_err(|_| format!("'{}' is not a number or 'Auto'", v))
+            }
+            PropertyKind::Bool => {
+                if v.eq_ignore_ascii_case("true") {
+                    Ok(PropertyValue::Bool(true))
+                } else if v.eq_ignore_ascii_case("false") {
+                    Ok(PropertyValue::Bool(false))
+                } else {
+                    Err(format!("'{}' is not 'True' or 'False'", v))
+                }
+            }
+            PropertyKind::Str => Ok(PropertyValue::Str(s.to_string())),
+            PropertyKind::Thickness => Thickness::parse(v).map(PropertyValue::Thickness),
+            PropertyKind::Color => Color::parse(v).map(PropertyValue::Color),
+            PropertyKind::GridLength => GridLength::parse(v).map(PropertyValue::GridLength),
+            PropertyKind::Enum(members) => {
+                for m in members.iter() {
+                    if v.eq_ignore_ascii_case(m) {
+                        return Ok(PropertyValue::Enum(m.to_string()));
+                    }
+                }
+                Err(format!("'{}' is not one of {}", v, members.join(", ")))
+            }
+            PropertyKind::Custom(parse) => parse(v),
+        }
+    }
+}
+
+pub struct PropertyDef {
+    pub name: &'static str,
+    pub kind: PropertyKind,
+    // parsed with 'kind', None when the property has no value until set
+    pub default: Option<&'static str>,
+}
+
+impl PropertyDef {
+    pub const fn new(name: &'static str, kind: PropertyKind, default: &'static str) -> PropertyDef {
+        PropertyDef {
+            name,
+            kind,
+            default: Some(default),
+        }
+    }
+
+    pub const fn unset(name: &'static str, kind: PropertyKind) -> PropertyDef {
+        PropertyDef {
+            name,
+            kind,
+            default: None,
+        }
+    }
+
+    pub fn get_default(&self) -> Option<PropertyValue> {
+        self.default.map(|d| self.kind.parse(d).unwrap())
+    }
+}
+
+// understood by every element
+pub static COMMON_PROPERTIES: [PropertyDef; 13] = [
+    PropertyDef::unset("Width", PropertyKind::Length),
+    PropertyDef::unset("Height", PropertyKind::Length),
+    PropertyDef::new("MinWidth", PropertyKind::Float, "0"),
+    PropertyDef::unset("MaxWidth", PropertyKind::Float),
+    PropertyDef::new("MinHeight", PropertyKind::Float, "0"),
+    PropertyDef::unset("MaxHeight", PropertyKind::Float),
+    PropertyDef::new("Margin", PropertyKind::Thickness, "0"),
+    PropertyDef::new("Padding", PropertyKind::Thickness, "0"),
+    PropertyDef::new("IsEnabled", PropertyKind::Bool, "True"),
+    PropertyDef::new("Visibility", PropertyKind::Enum(Visibility::NAMES), "Visible"),
+    PropertyDef::new(
+        "HorizontalAlignment",
+        PropertyKind::Enum(HorizontalAlignment::NAMES),
+        "Stretch",
+    ),
+    PropertyDef::new(
+        "VerticalAlignment",
+        PropertyKind::Enum(VerticalAlignment::NAMES),
+        "Stretch",
+    ),
+    PropertyDef::unset("ToolTip", PropertyKind::Str),
+];
+
+// set on children to be read by their parent
+pub static ATTACHED_PROPERTIES: [PropertyDef; 4] = [
+    PropertyDef::new("Grid.Row", PropertyKind::Int, "0"),
+    PropertyDef::new("Grid.Column", PropertyKind::Int, "0"),
+    PropertyDef::new("Grid.RowSpan", PropertyKind::Int, "1"),
+    PropertyDef::new("Grid.ColumnSpan", PropertyKind::Int, "1"),
+];
+
+// element specific definitions take precedence over the common ones
+pub fn find_property_def<'a>(element_defs: &'a [PropertyDef], name: &str) -> Option<&'a PropertyDef> {
+    element_defs
+        .iter()
+        .chain(COMMON_PROPERTIES.iter())
+        .chain(ATTACHED_PROPERTIES.iter())
+        .find(|d| d.name == name)
+}
+
+// attributes without a definition are kept as plain strings
+pub fn parse_property(element_defs: &[PropertyDef], name: &str, value: &str) -> Result<PropertyValue, String> {
+    match find_property_def(element_defs, name) {
+        Some(def) => def.kind.parse(value),
+        None => Ok(PropertyValue::Str(value.to_string())),
+    }
+}
+
+pub type PropertyChangedHandler = Box<dyn Fn(&str, &PropertyValue)>;
+
+// the explicitly set values of an element in the order they were set
+pub struct PropertyStore {
+    defs: &'static [PropertyDef],
+    values: Vec<(String, PropertyValue)>,
+    handlers: Vec<PropertyChangedHandler>,
+}
+
+impl PropertyStore {
+    pub fn new(defs: &'static [PropertyDef]) -> PropertyStore {
+        PropertyStore {
+            defs,
+            values: Vec::new(),
+            handlers: Vec::new(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_defs(&self) -> &'static [PropertyDef] {
+        self.defs
+    }
+
+    #[allow(dead_code)]
+    pub fn is_set(&self, name: &str) -> bool {
+        self.values.iter().any(|(n, _)| n == name)
+    }
+
+    // the set value, or else the default from the property definition
+    pub fn get(&self, name: &str) -> Option<PropertyValue> {
+        if let Some((_, v)) = self.values.iter().find(|(n, _)| n == name) {
+            return Some(v.clone());
+        }
+        find_property_def(self.defs, name).and_then(|d| d.get_default())
+    }
+
+    pub fn set(&mut self, name: &str, value: PropertyValue) {
+        match self.values.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => {
+                if *v == value {
+                    return;
+                }
+                *v = value.clone();
+            }
+            None => self.values.push((name.to_string(), value.clone())),
+        }
+
+        for h in self.handlers.iter() {
+            h(name, &value);
+        }
+    }
+
+    // parses 'value' according to the property definition before setting it
+    pub fn set_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let v = parse_property(self.defs, name, value)?;
+        self.set(name, v);
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, PropertyValue)> {
+        self.values.iter()
+    }
+
+    // handlers run with the element locked, they must not lock it again
+    pub fn add_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.handlers.push(handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converters::Orientation;
+    use crate::properties::{PropertyDef, PropertyKind, PropertyStore, PropertyValue};
+    use std::{cell::RefCell, rc::Rc};
+
+    static DEFS: [PropertyDef; 2] = [
+        PropertyDef::new("FontSize", PropertyKind::Float, "12"),
+        PropertyDef::new("Orientation", PropertyKind::Enum(Orientation::NAMES), "Vertical"),
+    ];
+
+    #[test]
+    fn typed_values_and_defaults() {
+        let mut store = PropertyStore::new(&DEFS);
+        assert_eq!(store.get("FontSize"), Some(PropertyValue::Float(12.0)));
+        assert_eq!(store.get("IsEnabled"), Some(PropertyValue::Bool(true)));
+        assert_eq!(store.get("Grid.RowSpan").unwrap().as_i32(), Some(1));
+        assert_eq!(store.get("Width").map(|w| w.to_string()), None);
+
+        assert!(store.set_str("Orientation", "horizontal").is_ok());
+        assert_eq!(store.get("Orientation").unwrap().to_string(), "Horizontal");
+        assert!(store.set_str("Orientation", "Diagonal").is_err());
+        assert!(store.set_str("Width", "Auto").is_ok());
+        assert_eq!(store.get("Width").unwrap().as_f64(), None);
+        assert_eq!(store.get("Width").unwrap().to_string(), "Auto");
+
+        assert!(store.set_str("Tag", "anything").is_ok());
+        assert_eq!(store.get("Tag"), Some(PropertyValue::Str("anything".to_string())));
+    }
+
+    #[test]
+    fn changes_are_notified() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut store = PropertyStore::new(&DEFS);
+        let s = seen.clone();
+        store.add_changed_handler(Box::new(move |name, v| {
+            s.borrow_mut().push(format!("{}={}", name, v));
+        }));
+
+        store.set("FontSize", PropertyValue::Float(14.0));
+        store.set("FontSize", PropertyValue::Float(14.0));
+        store.set_str("Orientation", "Horizontal").unwrap();
+        store.set_str("Width", "Auto").unwrap();
+        store.set_str("Width", "auto").unwrap();
+        assert_eq!(*seen.borrow(), vec!["FontSize=14", "Orientation=Horizontal", "Width=Auto"]);
+    }
+}
@@ -0,0 +1,161 @@
+use std::io::Write;
+
+use crate::converters::Color;
+use crate::layout::{Rect, CHAR_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub font_size: f64,
+    pub bold: bool,
+    pub color: Color,
+}
+
+// a plain RGBA pixel buffer the headless renderer draws into, everything
+// is snapped to whole pixels so the output is the same on every machine
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+fn blend(dst: [u8; 4], src: Color) -> [u8; 4] {
+    if src.a == 255 {
+        return [src.r, src.g, src.b, src.a];
+    }
+    let a = src.a as u32;
+    let mix = |s: u8, d: u8| ((s as u32 * a + d as u32 * (255 - a)) / 255) as u8;
+    [
+        mix(src.r, dst[0]),
+        mix(src.g, dst[1]),
+        mix(src.b, dst[2]),
+        (a + dst[3] as u32 * (255 - a) / 255) as u8,
+    ]
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32, background: Color) -> Canvas {
+        let bg = [background.r, background.g, background.b, background.a];
+        Canvas {
+            width,
+            height,
+            pixels: vec![bg; width as usize * height as usize],
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    #[allow(dead_code)]
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    #[allow(dead_code)]
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        let [r, g, b, a] = self.pixels[y as usize * self.width as usize + x as usize];
+        Color::from_argb(u32::from_be_bytes([a, r, g, b]))
+    }
+
+    fn blend_pixel(&mut self, x: i64, y: i64, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = y as usize * self.width as usize + x as usize;
+        self.pixels[i] = blend(self.pixels[i], color);
+    }
+
+    // only the part on the canvas is visited, however large 'rect' is
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let x0 = (rect.x.round() as i64).clamp(0, w);
+        let y0 = (rect.y.round() as i64).clamp(0, h);
+        let x1 = (rect.right().round() as i64).clamp(0, w);
+        let y1 = (rect.bottom().round() as i64).clamp(0, h);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend_pixel(x, y, color);
+            }
+        }
+    }
+
+    // the border is drawn inside the rectangle
+    pub fn stroke_rect(&mut self, rect: Rect, width: f64, color: Color) {
+        let w = width.min(rect.width / 2.0).min(rect.height / 2.0);
+        let inner_height = rect.height - 2.0 * w;
+        self.fill_rect(Rect::new(rect.x, rect.y, rect.width, w), color);
+        self.fill_rect(Rect::new(rect.x, rect.bottom() - w, rect.width, w), color);
+        self.fill_rect(Rect::new(rect.x, rect.y + w, w, inner_height), color);
+        self.fill_rect(Rect::new(rect.right() - w, rect.y + w, w, inner_height), color);
+    }
+
+    // only horizontal and vertical lines are needed, dashes are 4px long
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, color: Color, dashed: bool) {
+        // the ends are pulled just outside the canvas so huge lines stay cheap
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (x1, y1, x2, y2) = (
+            (x1.round() as i64).clamp(-1, w),
+            (y1.round() as i64).clamp(-1, h),
+            (x2.round() as i64).clamp(-1, w),
+            (y2.round() as i64).clamp(-1, h),
+        );
+        let steps = (x2 - x1).abs().max((y2 - y1).abs());
+        for i in 0..=steps {
+            if dashed && (i / 4) % 2 == 1 {
+                continue;
+            }
+            let x = x1 + (x2 - x1).signum() * i;
+            let y = y1 + (y2 - y1).signum() * i;
+            self.blend_pixel(x, y, color);
+        }
+    }
+
+    // draws 'text' with its line box at (x, y) using the built-in 8x8 font
+    // scaled to the fixed character cells of layout::FixedWidthMeasurer,
+    // nothing outside 'clip' is touched
+    pub fn draw_text(&mut self, x: f64, y: f64, text: &str, style: TextStyle, clip: Rect) {
+        let font_size = style.font_size;
+        let cell_width = font_size * CHAR_WIDTH;
+        let glyph_top = y + font_size * 0.1;
+        // the clip is kept on the canvas, however large the glyphs are
+        let (w, h) = (self.width as i64, self.height as i64);
+        let clip_x0 = (clip.x.round() as i64).clamp(0, w);
+        let clip_y0 = (clip.y.round() as i64).clamp(0, h);
+        let clip_x1 = (clip.right().round() as i64).clamp(0, w);
+        let clip_y1 = (clip.bottom().round() as i64).clamp(0, h);
+
+        for (i, c) in text.chars().enumerate() {
+            let glyph = match font8x8::legacy::BASIC_LEGACY.get(c as usize) {
+                Some(g) => *g,
+                None => font8x8::legacy::BASIC_LEGACY['?' as usize],
+            };
+            let gx0 = (x + i as f64 * cell_width).round() as i64;
+            let gx1 = (x + (i + 1) as f64 * cell_width).round() as i64;
+            let gy0 = glyph_top.round() as i64;
+            let gy1 = (glyph_top + font_size).round() as i64;
+            if gx1 <= gx0 || gy1 <= gy0 {
+                continue;
+            }
+            for py in gy0.max(clip_y0)..gy1.min(clip_y1) {
+                let row = glyph[((py - gy0) * 8 / (gy1 - gy0)) as usize];
+                for px in gx0.max(clip_x0)..gx1.min(clip_x1) {
+                    let bit = (px - gx0) * 8 / (gx1 - gx0);
+                    let set = row & (1 << bit) != 0
+                        || (style.bold && bit > 0 && row & (1 << (bit - 1)) != 0);
+                    if set {
+                        self.blend_pixel(px, py, style.color);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn write_png<W: Write>(&self, w: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(self.pixels.as_flattened())
+    }
+}
@@ -0,0 +1,490 @@
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::{
+    button::Button,
+    cli::MAX_SIZE,
+    content_page::ContentPage,
+    converters::Color,
+    grid_layout::{ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition},
+    label::Label,
+    layout::{layout, window_size, wrap_text, FixedWidthMeasurer, LayoutResult, Rect, Size, CHAR_WIDTH, LINE_HEIGHT},
+    raster::{Canvas, TextStyle},
+    stack_layout::StackLayout,
+    text_block::TextBlock,
+    ui_elements::{UIElement, UITree},
+    unknown_ui_elt::Unknown,
+    visitor::Visitor,
+    window::Window,
+};
+
+// the headless backend: a Visitor turns the laid out tree into a display
+// list, which is then rasterized into a PNG or written out as SVG
+
+const BLACK: Color = Color::from_argb(0xFF000000);
+const WHITE: Color = Color::from_argb(0xFFFFFFFF);
+const BUTTON_FACE: Color = Color::from_argb(0xFFDDDDDD);
+const BUTTON_BORDER: Color = Color::from_argb(0xFF707070);
+const GRID_LINE: Color = Color::from_argb(0xFF606060);
+
+pub const DEFAULT_SIZE: Size = Size {
+    width: 800.0,
+    height: 600.0,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawOp {
+    FillRect {
+        rect: Rect,
+        color: Color,
+    },
+    StrokeRect {
+        rect: Rect,
+        width: f64,
+        color: Color,
+    },
+    Line {
+        from: (f64, f64),
+        to: (f64, f64),
+        color: Color,
+        dashed: bool,
+    },
+    // one line of text, (x, y) is the top left of its line box
+    Text {
+        x: f64,
+        y: f64,
+        text: String,
+        style: TextStyle,
+        clip: Rect,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+pub struct SnapshotRenderer<'l> {
+    layout: &'l LayoutResult,
+    ops: Vec<DrawOp>,
+    // one entry per element being visited, true when it or an ancestor is
+    // not Visible
+    hidden: Vec<bool>,
+}
+
+impl<'l> SnapshotRenderer<'l> {
+    pub fn new(layout: &'l LayoutResult) -> SnapshotRenderer<'l> {
+        SnapshotRenderer {
+            layout,
+            ops: Vec::new(),
+            hidden: Vec::new(),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_ops(&self) -> &[DrawOp] {
+        &self.ops
+    }
+
+    // the rectangle to paint 'elt' in, None when it is not shown
+    fn begin(&mut self, elt: &dyn UIElement) -> Option<Rect> {
+        let visible = match elt.get_value("Visibility") {
+            Some(v) => v.to_string() == "Visible",
+            None => true,
+        };
+        let hidden = !visible || self.hidden.last().copied().unwrap_or(false);
+        self.hidden.push(hidden);
+        if hidden {
+            return None;
+        }
+        let rect = self.layout.get(&elt.get_id())?;
+        if let Some(c) = elt.get_value("Background").and_then(|v| v.as_color()) {
+            self.ops.push(DrawOp::FillRect { rect, color: c });
+        }
+        Some(rect)
+    }
+
+    fn end(&mut self) {
+        self.hidden.pop();
+    }
+
+    fn is_hidden(&self) -> bool {
+        self.hidden.last().copied().unwrap_or(false)
+    }
+
+    fn text(&mut self, rect: Rect, text: &str, style: TextStyle, align: TextAlign, wrap: bool, trim: bool) {
+        let font_size = style.font_size;
+        let cell_width = font_size * CHAR_WIDTH;
+        let max_chars = ((rect.width / cell_width).floor() as usize).max(1);
+        let mut lines = wrap_text(text, if wrap { Some(max_chars) } else { None });
+        if trim {
+            for l in lines.iter_mut() {
+                if l.chars().count() > max_chars {
+                    let kept: String = l.chars().take(max_chars.saturating_sub(3)).collect();
+                    *l = format!("{}...", kept);
+                }
+            }
+        }
+
+        let line_height = font_size * LINE_HEIGHT;
+        for (i, line) in lines.iter().enumerate() {
+            let width = line.chars().count() as f64 * cell_width;
+            let x = match align {
+                TextAlign::Left => rect.x,
+                TextAlign::Center => rect.x + (rect.width - width) / 2.0,
+                TextAlign::Right => rect.right() - width,
+            };
+            self.ops.push(DrawOp::Text {
+                x,
+                y: rect.y + i as f64 * line_height,
+                text: line.to_string(),
+                style,
+                clip: rect,
+            });
+        }
+    }
+}
+
+impl<'l> Visitor for SnapshotRenderer<'l> {
+    fn start_visit_button(&mut self, b: &Button) {
+        if let Some(rect) = self.begin(b) {
+            self.ops.push(DrawOp::FillRect {
+                rect,
+                color: BUTTON_FACE,
+            });
+            self.ops.push(DrawOp::StrokeRect {
+                rect,
+                width: 1.0,
+                color: BUTTON_BORDER,
+            });
+            // centered vertically as well
+            let style = TextStyle {
+                font_size: b.get_font_size().unwrap_or(12.0),
+                bold: false,
+                color: BLACK,
+            };
+            let text_height = style.font_size * LINE_HEIGHT;
+            let line = Rect::new(
+                rect.x,
+                rect.y + (rect.height - text_height) / 2.0,
+                rect.width,
+                text_height,
+            );
+            self.text(line, &b.get_text(), style, TextAlign::Center, false, false);
+        }
+    }
+    fn start_visit_window(&mut self, w: &Window) {
+        if let Some(rect) = self.begin(w) {
+            if w.get_value("Background").is_none() {
+                self.ops.push(DrawOp::FillRect { rect, color: WHITE });
+            }
+        }
+    }
+    fn start_visit_label(&mut self, l: &Label) {
+        if let Some(rect) = self.begin(l) {
+            let style = TextStyle {
+                font_size: l.get_font_size().unwrap_or(12.0),
+                bold: false,
+                color: BLACK,
+            };
+            self.text(rect, &l.get_content(), style, TextAlign::Left, false, false);
+        }
+    }
+    fn start_visit_text_block(&mut self, t: &TextBlock) {
+        if let Some(rect) = self.begin(t) {
+            let align = match t.get_text_alignment().as_str() {
+                "Right" => TextAlign::Right,
+                "Center" => TextAlign::Center,
+                _ => TextAlign::Left,
+            };
+            let style = TextStyle {
+                font_size: t.get_font_size().unwrap_or(12.0),
+                bold: t.get_font_weight().unwrap_or(400) >= 600,
+                color: t.get_foreground().unwrap_or(BLACK),
+            };
+            self.text(rect, &t.get_text(), style, align, t.wraps(), t.trims());
+        }
+    }
+    fn start_visit_grid(&mut self, g: &GridLayout) {
+        self.begin(g);
+    }
+    fn start_visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn start_visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn start_visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn start_visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn start_visit_content_page(&mut self, g: &ContentPage) {
+        self.begin(g);
+    }
+    fn start_visit_unknown(&mut self, g: &Unknown) {
+        self.begin(g);
+    }
+    fn start_visit_stack(&mut self, g: &StackLayout) {
+        self.begin(g);
+    }
+
+    fn visit_button(&mut self, _b: &Button) {
+        self.end();
+    }
+    fn visit_window(&mut self, _w: &Window) {
+        self.end();
+    }
+    fn visit_label(&mut self, _l: &Label) {
+        self.end();
+    }
+    fn visit_text_block(&mut self, _t: &TextBlock) {
+        self.end();
+    }
+    // the grid lines go over the cell contents, as in WPF
+    fn visit_grid(&mut self, g: &GridLayout) {
+        if g.shows_grid_lines() && !self.is_hidden() {
+            if let Some(tracks) = self.layout.get_grid_tracks(&g.get_id()) {
+                let (top, bottom) = (tracks.rows[0], *tracks.rows.last().unwrap());
+                let (left, right) = (tracks.columns[0], *tracks.columns.last().unwrap());
+                for x in tracks.columns.iter() {
+                    self.ops.push(DrawOp::Line {
+                        from: (*x, top),
+                        to: (*x, bottom),
+                        color: GRID_LINE,
+                        dashed: true,
+                    });
+                }
+                for y in tracks.rows.iter() {
+                    self.ops.push(DrawOp::Line {
+                        from: (left, *y),
+                        to: (right, *y),
+                        color: GRID_LINE,
+                        dashed: true,
+                    });
+                }
+            }
+        }
+        self.end();
+    }
+    fn visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn visit_content_page(&mut self, _g: &ContentPage) {
+        self.end();
+    }
+    fn visit_unknown(&mut self, _g: &Unknown) {
+        self.end();
+    }
+    fn visit_stack(&mut self, _g: &StackLayout) {
+        self.end();
+    }
+}
+
+pub fn rasterize(ops: &[DrawOp], size: Size) -> Canvas {
+    let mut canvas = Canvas::new(
+        size.width.round() as u32,
+        size.height.round() as u32,
+        Color::from_argb(0),
+    );
+    for op in ops.iter() {
+        match op {
+            DrawOp::FillRect { rect, color } => canvas.fill_rect(*rect, *color),
+            DrawOp::StrokeRect { rect, width, color } => canvas.stroke_rect(*rect, *width, *color),
+            DrawOp::Line {
+                from,
+                to,
+                color,
+                dashed,
+            } => canvas.line(from.0, from.1, to.0, to.1, *color, *dashed),
+            DrawOp::Text {
+                x,
+                y,
+                text,
+                style,
+                clip,
+            } => canvas.draw_text(*x, *y, text, *style, *clip),
+        }
+    }
+    canvas
+}
+
+fn svg_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn svg_color(c: Color) -> String {
+    if c.a == 255 {
+        return format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+    }
+    c.to_css()
+}
+
+pub fn to_svg(ops: &[DrawOp], size: Size) -> String {
+    let mut ret = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        size.width, size.height, size.width, size.height
+    );
+    for op in ops.iter() {
+        let line = match op {
+            DrawOp::FillRect { rect, color } => format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                rect.x, rect.y, rect.width, rect.height, svg_color(*color)
+            ),
+            // SVG strokes are centered on the outline, ours are inside it
+            DrawOp::StrokeRect { rect, width, color } => format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+                rect.x + width / 2.0,
+                rect.y + width / 2.0,
+                (rect.width - width).max(0.0),
+                (rect.height - width).max(0.0),
+                svg_color(*color),
+                width
+            ),
+            DrawOp::Line {
+                from,
+                to,
+                color,
+                dashed,
+            } => format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"{}/>",
+                from.0,
+                from.1,
+                to.0,
+                to.1,
+                svg_color(*color),
+                if *dashed { " stroke-dasharray=\"4\"" } else { "" }
+            ),
+            // the nested svg clips the text to its element
+            DrawOp::Text {
+                x,
+                y,
+                text,
+                style,
+                clip,
+            } => format!(
+                "<svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"><text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\"{} fill=\"{}\">{}</text></svg>",
+                clip.x,
+                clip.y,
+                clip.width,
+                clip.height,
+                x - clip.x,
+                y - clip.y + style.font_size,
+                style.font_size,
+                if style.bold { " font-weight=\"bold\"" } else { "" },
+                svg_color(style.color),
+                svg_escape(text)
+            ),
+        };
+        ret.push_str("  ");
+        ret.push_str(&line);
+        ret.push('\n');
+    }
+    ret.push_str("</svg>\n");
+    ret
+}
+
+// lays out and paints the tree, without a size the Width and Height of a
+// root Window are used
+pub fn render_tree(tree: &UITree, size: Option<Size>) -> Result<(Vec<DrawOp>, Size), String> {
+    let root = match &tree.root {
+        Some(r) => r,
+        None => return Err("the document has no root element".to_string()),
+    };
+    let size = size.or_else(|| window_size(root)).unwrap_or(DEFAULT_SIZE);
+    // a Window's own Width and Height get the same limit as --size
+    let max = MAX_SIZE as f64;
+    if !(0.0..=max).contains(&size.width) || !(0.0..=max).contains(&size.height) {
+        return Err(format!(
+            "the window is {}x{}, at most {}x{} can be rendered",
+            size.width, size.height, MAX_SIZE, MAX_SIZE
+        ));
+    }
+    let result = layout(root, size, &FixedWidthMeasurer);
+    let mut renderer = SnapshotRenderer::new(&result);
+    root.lock().visit(&mut renderer);
+    Ok((renderer.ops, size))
+}
+
+// writes a PNG, or SVG when 'output' ends in .svg
+pub fn render_to_file(tree: &UITree, output: &str, size: Option<Size>) -> Result<(), String> {
+    let (ops, size) = render_tree(tree, size)?;
+    let file = File::create(output).map_err(|e| format!("{}: {}", output, e))?;
+    let mut w = BufWriter::new(file);
+    if output.to_ascii_lowercase().ends_with(".svg") {
+        use std::io::Write;
+        w.write_all(to_svg(&ops, size).as_bytes())
+            .map_err(|e| format!("{}: {}", output, e))
+    } else {
+        rasterize(&ops, size)
+            .write_png(w)
+            .map_err(|e| format!("{}: {}", output, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::converters::Color;
+    use crate::layout::Size;
+    use crate::render::{rasterize, render_tree, to_svg, DrawOp};
+    use crate::xaml_reader;
+
+    #[test]
+    fn renders_backgrounds_borders_and_text() {
+        let text = "<Window Width=\"120\" Height=\"60\"><Grid Background=\"LightSteelBlue\" ShowGridLines=\"True\">\
+                    <Button Width=\"60\" Height=\"20\" HorizontalAlignment=\"Left\" VerticalAlignment=\"Top\">Ok</Button>\
+                    <TextBlock Text=\"a&lt;b\" VerticalAlignment=\"Bottom\" Foreground=\"Red\"/>\
+                    </Grid></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let (ops, size) = render_tree(&t, None).unwrap();
+        assert_eq!(size, Size::new(120.0, 60.0));
+        assert!(ops.iter().any(|op| matches!(op, DrawOp::Text { text, .. } if text == "Ok")));
+
+        let canvas = rasterize(&ops, size);
+        let steel = Color::parse("LightSteelBlue").unwrap();
+        assert_eq!(canvas.get_pixel(100, 30), steel);
+        // the button border and face
+        assert_eq!(canvas.get_pixel(30, 19).to_argb(), 0xFF707070);
+        assert_eq!(canvas.get_pixel(2, 2).to_argb(), 0xFFDDDDDD);
+        // some pixels of the red text
+        let red = Color::parse("Red").unwrap();
+        let mut found = false;
+        for y in 45..60 {
+            for x in 0..30 {
+                found |= canvas.get_pixel(x, y) == red;
+            }
+        }
+        assert!(found);
+
+        let svg = to_svg(&ops, size);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"120\""));
+        assert!(svg.contains(">a&lt;b</text>"));
+        assert!(svg.contains("fill=\"#b0c4de\""));
+    }
+
+    #[test]
+    fn buttons_and_labels_use_their_font_size() {
+        let text = "<StackPanel><Button FontSize=\"20\">Ok</Button><Label FontSize=\"16\" Text=\"L\"/></StackPanel>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let (ops, _) = render_tree(&t, None).unwrap();
+        let size_of = |s: &str| {
+            ops.iter().find_map(|op| match op {
+                DrawOp::Text { text, style, .. } if text == s => Some(style.font_size),
+                _ => None,
+            })
+        };
+        assert_eq!(size_of("Ok"), Some(20.0));
+        assert_eq!(size_of("L"), Some(16.0));
+    }
+
+    #[test]
+    fn huge_windows_and_text_are_kept_in_bounds() {
+        let t = xaml_reader::read_xaml_str("inline", "<Window Width=\"100000\" Height=\"10\"/>").unwrap();
+        assert!(render_tree(&t, None).is_err());
+
+        let text = "<Window Width=\"40\" Height=\"20\"><TextBlock FontSize=\"100000\" Margin=\"-50000,0,0,0\" Text=\"xy\"/></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let (ops, size) = render_tree(&t, None).unwrap();
+        let canvas = rasterize(&ops, size);
+        assert_eq!(canvas.get_width(), 40);
+    }
+}
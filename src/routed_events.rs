@@ -0,0 +1,10 @@
+// the events the backends raise and dispatch_routed_event routes. The
+// proc-macro crate includes this file too, #[inspectable(xaml = ...)] checks
+// the handler names given for them
+
+pub const BUTTON_CLICK: &str = "Button.Click";
+pub const KEY_DOWN: &str = "KeyDown";
+
+// only read by the macro
+#[allow(dead_code)]
+pub const ROUTED_EVENTS: [&str; 2] = [BUTTON_CLICK, KEY_DOWN];
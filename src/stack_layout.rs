@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{events::Event, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef}, visitor::Visitor};
+use crate::{converters::Orientation, properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue}, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup}, visitor::Visitor};
 
 
 pub struct StackLayout {
@@ -8,26 +8,55 @@ pub struct StackLayout {
 }
 
 
+static STACK_LAYOUT_PROPERTIES: [PropertyDef; 2] = [
+    PropertyDef::new("Orientation", PropertyKind::Enum(Orientation::NAMES), "Vertical"),
+    PropertyDef::new("Spacing", PropertyKind::Float, "0"),
+];
+
 impl UIAlloc for StackLayout {
     fn new(attributes: &HashMap<String, String>, id: String) -> StackLayout {
         StackLayout {
-            common: UICommon::new(attributes, "StackLayout", id),
+            common: UICommon::new(attributes, "StackLayout", id, &STACK_LAYOUT_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &STACK_LAYOUT_PROPERTIES
+    }
+}
+
+impl StackLayout {
+    pub fn get_orientation(&self) -> Orientation {
+        Orientation::parse(&self.common.get_str("Orientation")).unwrap_or(Orientation::Vertical)
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.get_orientation() == Orientation::Horizontal
+    }
+
+    // in pixels, it can be fractional
+    pub fn get_spacing(&self) -> f64 {
+        self.common.get_f64("Spacing").unwrap_or(0.0)
+    }
 }
 
 impl UIElement for StackLayout {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        println!("NOTICE: stacklayout-handle-event");
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
 
-        self.common.handle_event(ev);
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
 
@@ -35,8 +64,32 @@ impl UIElement for StackLayout {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     
     fn get_ui_type_name(&self) -> &'static str {
@@ -47,6 +100,11 @@ impl UIElement for StackLayout {
         self.common.add_child(child, parent)
     }
     
+
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
+
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {} ", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
@@ -60,3 +118,29 @@ impl UIElement for StackLayout {
         visitor.visit_stack(self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::converters::Orientation;
+    use crate::stack_layout::StackLayout;
+    use crate::ui_elements::UIAlloc;
+
+    fn stack(attributes: &[(&str, &str)]) -> StackLayout {
+        let attributes: HashMap<String, String> =
+            attributes.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        StackLayout::new(&attributes, "ID_0".to_string())
+    }
+
+    #[test]
+    fn parses_orientation_and_spacing() {
+        let s = stack(&[]);
+        assert_eq!(s.get_orientation(), Orientation::Vertical);
+        assert_eq!(s.get_spacing(), 0.0);
+
+        let s = stack(&[("Orientation", "Horizontal"), ("Spacing", "2.5")]);
+        assert!(s.is_horizontal());
+        assert_eq!(s.get_spacing(), 2.5);
+    }
+}
@@ -1,40 +1,141 @@
 use std::collections::HashMap;
 
-use crate::{events::Event, ui_elements::{get_attribute, tabs, UIAlloc, UICommon, UIElement, UIElementRef}, visitor::Visitor};
+use crate::{converters::Color, properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue}, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup}, visitor::Visitor};
 
 pub struct TextBlock {
-    text: String,
-    _font_weight: String,
-    _font_size: String,
-    _foreground: String,
     common: UICommon,
 }
 
+// WPF font weight names and their OpenType/CSS numeric weights
+const FONT_WEIGHTS: [(&str, i32); 16] = [
+    ("Thin", 100),
+    ("ExtraLight", 200),
+    ("UltraLight", 200),
+    ("Light", 300),
+    ("Normal", 400),
+    ("Regular", 400),
+    ("Medium", 500),
+    ("SemiBold", 600),
+    ("DemiBold", 600),
+    ("Bold", 700),
+    ("ExtraBold", 800),
+    ("UltraBold", 800),
+    ("Black", 900),
+    ("Heavy", 900),
+    ("ExtraBlack", 950),
+    ("UltraBlack", 950),
+];
+
+pub fn parse_font_weight(s: &str) -> Option<i32> {
+    let s = s.trim();
+    for (name, weight) in FONT_WEIGHTS.iter() {
+        if s.eq_ignore_ascii_case(name) {
+            return Some(*weight);
+        }
+    }
+    match s.parse::<i32>() {
+        Ok(w) if (1..=999).contains(&w) => Some(w),
+        _ => None,
+    }
+}
+
+// names keep their spelling so they can be written back, numbers become an Int
+fn font_weight_property(s: &str) -> Result<PropertyValue, String> {
+    if parse_font_weight(s).is_none() {
+        return Err("expected a weight name such as 'Bold' or a number from 1 to 999".to_string());
+    }
+    match s.parse::<i32>() {
+        Ok(w) => Ok(PropertyValue::Int(w)),
+        Err(_) => Ok(PropertyValue::Enum(s.to_string())),
+    }
+}
+
+static TEXT_BLOCK_PROPERTIES: [PropertyDef; 7] = [
+    PropertyDef::unset("Text", PropertyKind::Str),
+    // unset so backends only style text that asks for it, 12 and Normal
+    // are used where it is drawn
+    PropertyDef::unset("FontSize", PropertyKind::Float),
+    PropertyDef::unset("FontWeight", PropertyKind::Custom(font_weight_property)),
+    PropertyDef::unset("Foreground", PropertyKind::Color),
+    PropertyDef::new(
+        "TextWrapping",
+        PropertyKind::Enum(&["NoWrap", "Wrap", "WrapWithOverflow"]),
+        "NoWrap",
+    ),
+    PropertyDef::new(
+        "TextAlignment",
+        PropertyKind::Enum(&["Left", "Right", "Center", "Justify"]),
+        "Left",
+    ),
+    PropertyDef::new(
+        "TextTrimming",
+        PropertyKind::Enum(&["None", "CharacterEllipsis", "WordEllipsis"]),
+        "None",
+    ),
+];
 
 impl UIAlloc for TextBlock {
     fn new(attributes: &HashMap<String, String>, id: String) -> TextBlock {
         TextBlock {
-            text: get_attribute(&attributes, "Text", ""),
-            _font_weight: get_attribute(&attributes, "TextWeight", ""),
-            _font_size: get_attribute(&attributes, "FontSize", ""),
-            _foreground: get_attribute(&attributes, "Foreground", ""),
-            common: UICommon::new(attributes, "TextBlock", id),
+            common: UICommon::new(attributes, "TextBlock", id, &TEXT_BLOCK_PROPERTIES),
         }
     }
+
+    fn properties() -> &'static [PropertyDef] {
+        &TEXT_BLOCK_PROPERTIES
+    }
+}
+
+impl TextBlock {
+    pub fn get_text(&self) -> String {
+        self.common.get_str("Text")
+    }
+
+    pub fn get_font_size(&self) -> Option<f64> {
+        self.common.get_f64("FontSize")
+    }
+
+    pub fn get_font_weight(&self) -> Option<i32> {
+        parse_font_weight(&self.common.get_str("FontWeight"))
+    }
+
+    pub fn get_foreground(&self) -> Option<Color> {
+        self.common.get_value("Foreground").and_then(|v| v.as_color())
+    }
+
+    // NoWrap, Wrap or WrapWithOverflow
+    pub fn wraps(&self) -> bool {
+        self.common.get_str("TextWrapping").starts_with("Wrap")
+    }
+
+    // Left, Right, Center or Justify
+    pub fn get_text_alignment(&self) -> String {
+        self.common.get_str("TextAlignment")
+    }
+
+    // CharacterEllipsis and WordEllipsis, both shown as a trailing ellipsis
+    pub fn trims(&self) -> bool {
+        self.common.get_str("TextTrimming").ends_with("Ellipsis")
+    }
 }
 
 impl UIElement for TextBlock {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        println!("NOTICE: textblock-handle-event");
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
 
-        self.common.handle_event(ev);
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
 
@@ -42,8 +143,32 @@ impl UIElement for TextBlock {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     
     fn get_ui_type_name(&self) -> &'static str {
@@ -54,18 +179,23 @@ impl UIElement for TextBlock {
         self.common.add_child(child, parent);
     }
 
+
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
+
     fn dump(&self, indent: i32) {
         println!(
             "{}DUMP: {} - content:{}",
             tabs(indent),
             self.get_ui_type_name(),
-            self.text
+            self.get_text()
         );
         self.common.dump(indent);
     }
     
     fn add_content_string(&mut self, s: String) {
-        self.text = s
+        self.common.set_content("Text", &s);
     }
     
     fn visit(&self, visitor: &mut dyn Visitor) {
@@ -74,3 +204,17 @@ impl UIElement for TextBlock {
         visitor.visit_text_block(self);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::text_block::parse_font_weight;
+
+    #[test]
+    fn parses_font_weights() {
+        assert_eq!(parse_font_weight("Bold"), Some(700));
+        assert_eq!(parse_font_weight("semibold"), Some(600));
+        assert_eq!(parse_font_weight("350"), Some(350));
+        assert_eq!(parse_font_weight("1000"), None);
+        assert_eq!(parse_font_weight("Fat"), None);
+    }
+}
@@ -0,0 +1,657 @@
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::{panic, thread};
+
+use crossterm::{
+    cursor,
+    event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{self, Attribute, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    terminal,
+};
+
+use crate::{
+    button::Button,
+    callable::MainCallable,
+    content_page::ContentPage,
+    converters::Color,
+    events::dispatch_event,
+    grid_layout::{ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition},
+    label::Label,
+    layout::{layout, wrap_text, LayoutResult, Rect, Size, TextMeasurer},
+    routed_events::BUTTON_CLICK,
+    stack_layout::StackLayout,
+    text_block::TextBlock,
+    ui_elements::{UIElement, UITree},
+    unknown_ui_elt::Unknown,
+    visitor::Visitor,
+    window::Window,
+};
+
+// the terminal backend lays the tree out in pixels as usual, with every
+// character cell counting as CELL_WIDTH x CELL_HEIGHT pixels
+pub const CELL_WIDTH: f64 = 8.0;
+pub const CELL_HEIGHT: f64 = 16.0;
+
+// text takes one cell per character whatever its font size
+pub struct CellMeasurer;
+
+impl TextMeasurer for CellMeasurer {
+    fn measure(&self, text: &str, _font_size: f64, wrap_width: Option<f64>) -> Size {
+        let max_chars = wrap_width.map(|w| ((w / CELL_WIDTH).floor() as usize).max(1));
+        let lines = wrap_text(text, max_chars);
+        let longest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        Size::new(
+            longest as f64 * CELL_WIDTH,
+            lines.len().max(1) as f64 * CELL_HEIGHT,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+}
+
+impl Cell {
+    fn blank() -> Cell {
+        Cell {
+            ch: ' ',
+            fg: None,
+            bg: None,
+            bold: false,
+            reverse: false,
+        }
+    }
+}
+
+// a rectangle in character cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellRect {
+    pub col: u16,
+    pub row: u16,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+// the element ids that take keyboard focus, in document order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Focusable {
+    pub id: String,
+    pub rect: CellRect,
+    pub clickable: bool,
+}
+
+pub struct Screen {
+    cols: u16,
+    rows: u16,
+    cells: Vec<Cell>,
+    pub focusables: Vec<Focusable>,
+}
+
+impl Screen {
+    fn new(cols: u16, rows: u16) -> Screen {
+        Screen {
+            cols,
+            rows,
+            cells: vec![Cell::blank(); cols as usize * rows as usize],
+            focusables: Vec::new(),
+        }
+    }
+
+    fn cell_mut(&mut self, col: i64, row: i64) -> Option<&mut Cell> {
+        if col < 0 || row < 0 || col >= self.cols as i64 || row >= self.rows as i64 {
+            return None;
+        }
+        Some(&mut self.cells[row as usize * self.cols as usize + col as usize])
+    }
+
+    fn put(&mut self, col: i64, row: i64, ch: char) {
+        if let Some(c) = self.cell_mut(col, row) {
+            c.ch = ch;
+        }
+    }
+
+    fn fill_bg(&mut self, r: CellRect, color: Color) {
+        for row in r.row..r.row.saturating_add(r.rows).min(self.rows) {
+            for col in r.col..r.col.saturating_add(r.cols).min(self.cols) {
+                if let Some(c) = self.cell_mut(col as i64, row as i64) {
+                    c.bg = Some(color);
+                }
+            }
+        }
+    }
+
+    fn highlight(&mut self, r: CellRect) {
+        for row in r.row..r.row.saturating_add(r.rows).min(self.rows) {
+            for col in r.col..r.col.saturating_add(r.cols).min(self.cols) {
+                if let Some(c) = self.cell_mut(col as i64, row as i64) {
+                    c.reverse = true;
+                }
+            }
+        }
+    }
+
+    // the characters only, one string per row
+    #[allow(dead_code)]
+    pub fn to_lines(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.cols as usize)
+            .map(|r| r.iter().map(|c| c.ch).collect())
+            .collect()
+    }
+
+    fn frame(&mut self, title: &str) {
+        let (w, h) = (self.cols as i64, self.rows as i64);
+        for col in 1..w - 1 {
+            self.put(col, 0, '─');
+            self.put(col, h - 1, '─');
+        }
+        for row in 1..h - 1 {
+            self.put(0, row, '│');
+            self.put(w - 1, row, '│');
+        }
+        self.put(0, 0, '┌');
+        self.put(w - 1, 0, '┐');
+        self.put(0, h - 1, '└');
+        self.put(w - 1, h - 1, '┘');
+        if !title.is_empty() {
+            for (i, ch) in format!(" {} ", title).chars().enumerate() {
+                if 2 + i as i64 >= w - 2 {
+                    break;
+                }
+                self.put(2 + i as i64, 0, ch);
+            }
+        }
+    }
+}
+
+// converts the pixel rectangles of the layout into cells, the content of
+// the window frame starts at (1, 1)
+fn to_cells(r: Rect) -> CellRect {
+    let col = (r.x / CELL_WIDTH).round();
+    let row = (r.y / CELL_HEIGHT).round();
+    let cols = (r.right() / CELL_WIDTH).round() - col;
+    let rows = (r.bottom() / CELL_HEIGHT).round() - row;
+    CellRect {
+        col: (col as u16).saturating_add(1),
+        row: (row as u16).saturating_add(1),
+        cols: cols.max(0.0) as u16,
+        rows: rows.max(0.0) as u16,
+    }
+}
+
+struct TuiRenderer<'l> {
+    layout: &'l LayoutResult,
+    screen: Screen,
+    hidden: Vec<bool>,
+}
+
+impl<'l> TuiRenderer<'l> {
+    fn begin(&mut self, elt: &dyn UIElement) -> Option<CellRect> {
+        let visible = match elt.get_value("Visibility") {
+            Some(v) => v.to_string() == "Visible",
+            None => true,
+        };
+        let hidden = !visible || self.hidden.last().copied().unwrap_or(false);
+        self.hidden.push(hidden);
+        if hidden {
+            return None;
+        }
+        let rect = to_cells(self.layout.get(&elt.get_id())?);
+        if let Some(c) = elt.get_value("Background").and_then(|v| v.as_color()) {
+            self.screen.fill_bg(rect, c);
+        }
+        Some(rect)
+    }
+
+    fn end(&mut self) {
+        self.hidden.pop();
+    }
+
+    fn text(&mut self, r: CellRect, text: &str, fg: Option<Color>, bold: bool, wrap: bool) {
+        let max = if wrap { Some(r.cols.max(1) as usize) } else { None };
+        for (i, line) in wrap_text(text, max).iter().enumerate() {
+            if i as u16 >= r.rows.max(1) {
+                break;
+            }
+            for (j, ch) in line.chars().enumerate() {
+                if j as u16 >= r.cols {
+                    break;
+                }
+                if let Some(c) = self.screen.cell_mut(r.col as i64 + j as i64, r.row as i64 + i as i64) {
+                    c.ch = ch;
+                    c.fg = fg;
+                    c.bold = bold;
+                }
+            }
+        }
+    }
+
+    fn focusable(&mut self, elt: &dyn UIElement, rect: CellRect, clickable: bool) {
+        self.screen.focusables.push(Focusable {
+            id: elt.get_id(),
+            rect,
+            clickable,
+        });
+    }
+}
+
+impl<'l> Visitor for TuiRenderer<'l> {
+    fn start_visit_button(&mut self, b: &Button) {
+        if let Some(r) = self.begin(b) {
+            let label = b.get_text();
+            let n = label.chars().count() as u16;
+            let text = if r.cols >= n.saturating_add(2) {
+                format!("[{}]", label)
+            } else {
+                label
+            };
+            let width = text.chars().count() as u16;
+            let centered = CellRect {
+                col: r.col.saturating_add(r.cols.saturating_sub(width) / 2),
+                row: r.row.saturating_add(r.rows.saturating_sub(1) / 2),
+                cols: width.min(r.cols),
+                rows: 1,
+            };
+            self.text(centered, &text, None, true, false);
+            self.focusable(b, centered, true);
+        }
+    }
+    fn start_visit_window(&mut self, w: &Window) {
+        self.begin(w);
+        self.screen.frame(&w.get_title());
+    }
+    fn start_visit_label(&mut self, l: &Label) {
+        if let Some(r) = self.begin(l) {
+            self.text(r, &l.get_content(), None, false, false);
+            self.focusable(l, r, false);
+        }
+    }
+    fn start_visit_text_block(&mut self, t: &TextBlock) {
+        if let Some(r) = self.begin(t) {
+            let bold = t.get_font_weight().unwrap_or(400) >= 600;
+            self.text(r, &t.get_text(), t.get_foreground(), bold, t.wraps());
+            self.focusable(t, r, false);
+        }
+    }
+    fn start_visit_grid(&mut self, g: &GridLayout) {
+        self.begin(g);
+    }
+    fn start_visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn start_visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn start_visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn start_visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn start_visit_content_page(&mut self, g: &ContentPage) {
+        self.begin(g);
+    }
+    fn start_visit_unknown(&mut self, g: &Unknown) {
+        self.begin(g);
+    }
+    fn start_visit_stack(&mut self, g: &StackLayout) {
+        self.begin(g);
+    }
+
+    fn visit_button(&mut self, _b: &Button) {
+        self.end();
+    }
+    fn visit_window(&mut self, _w: &Window) {
+        self.end();
+    }
+    fn visit_label(&mut self, _l: &Label) {
+        self.end();
+    }
+    fn visit_text_block(&mut self, _t: &TextBlock) {
+        self.end();
+    }
+    // grid lines only go where no text was written
+    fn visit_grid(&mut self, g: &GridLayout) {
+        let hidden = self.hidden.last().copied().unwrap_or(false);
+        if g.shows_grid_lines() && !hidden {
+            if let Some(tracks) = self.layout.get_grid_tracks(&g.get_id()) {
+                let cols: Vec<i64> = tracks.columns.iter().map(|x| (x / CELL_WIDTH).round() as i64 + 1).collect();
+                let rows: Vec<i64> = tracks.rows.iter().map(|y| (y / CELL_HEIGHT).round() as i64 + 1).collect();
+                // only the part on the screen is visited
+                let (top, bottom) = (rows[0].max(0), rows[rows.len() - 1].min(self.screen.rows as i64));
+                let (left, right) = (cols[0].max(0), cols[cols.len() - 1].min(self.screen.cols as i64));
+                for col in cols.iter() {
+                    for row in top..bottom {
+                        if let Some(c) = self.screen.cell_mut(*col, row) {
+                            if c.ch == ' ' {
+                                c.ch = '┆';
+                            }
+                        }
+                    }
+                }
+                for row in rows.iter() {
+                    for col in left..right {
+                        if let Some(c) = self.screen.cell_mut(col, *row) {
+                            if c.ch == ' ' {
+                                c.ch = '┄';
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        self.end();
+    }
+    fn visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {}
+    fn visit_grid_row(&mut self, _g: &GridRowDefinitions) {}
+    fn visit_col_def(&mut self, _g: &ColumnDefinition) {}
+    fn visit_row_def(&mut self, _g: &RowDefinition) {}
+    fn visit_content_page(&mut self, _g: &ContentPage) {
+        self.end();
+    }
+    fn visit_unknown(&mut self, _g: &Unknown) {
+        self.end();
+    }
+    fn visit_stack(&mut self, _g: &StackLayout) {
+        self.end();
+    }
+}
+
+// draws the tree into a 'cols' x 'rows' screen, the outer cells are the
+// window frame
+pub fn render_screen(tree: &UITree, cols: u16, rows: u16) -> Screen {
+    let cols = cols.max(3);
+    let rows = rows.max(3);
+    let mut screen = Screen::new(cols, rows);
+    let root = match &tree.root {
+        Some(r) => r,
+        None => return screen,
+    };
+    let size = Size::new(
+        (cols - 2) as f64 * CELL_WIDTH,
+        (rows - 2) as f64 * CELL_HEIGHT,
+    );
+    let result = layout(root, size, &CellMeasurer);
+    let mut renderer = TuiRenderer {
+        layout: &result,
+        screen,
+        hidden: Vec::new(),
+    };
+    root.lock().visit(&mut renderer);
+    screen = renderer.screen;
+    screen
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Next,
+    Previous,
+    Activate,
+    Quit,
+}
+
+fn key_of(ev: &event::KeyEvent) -> Option<Key> {
+    match ev.code {
+        KeyCode::Tab if ev.modifiers.contains(KeyModifiers::SHIFT) => Some(Key::Previous),
+        KeyCode::Tab | KeyCode::Down | KeyCode::Right => Some(Key::Next),
+        KeyCode::BackTab | KeyCode::Up | KeyCode::Left => Some(Key::Previous),
+        KeyCode::Enter | KeyCode::Char(' ') => Some(Key::Activate),
+        KeyCode::Esc | KeyCode::Char('q') => Some(Key::Quit),
+        KeyCode::Char('c') if ev.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Quit),
+        _ => None,
+    }
+}
+
+// keyboard focus, kept by element id so it survives redraws
+pub struct TuiState {
+    pub focused: Option<String>,
+}
+
+impl TuiState {
+    pub fn new() -> TuiState {
+        TuiState { focused: None }
+    }
+
+    fn focus_index(&self, screen: &Screen) -> Option<usize> {
+        let id = self.focused.as_ref()?;
+        screen.focusables.iter().position(|f| &f.id == id)
+    }
+
+    // false when the UI should close, an activated Button raises
+    // 'Button.Click' exactly like a click in the GTK backend
+    pub fn handle_key(&mut self, key: Key, screen: &Screen, mw: MainCallable) -> bool {
+        let n = screen.focusables.len();
+        match key {
+            Key::Quit => return false,
+            Key::Next | Key::Previous if n > 0 => {
+                let i = match (self.focus_index(screen), key) {
+                    (None, Key::Next) => 0,
+                    (None, _) => n - 1,
+                    (Some(i), Key::Next) => (i + 1) % n,
+                    (Some(i), _) => (i + n - 1) % n,
+                };
+                self.focused = Some(screen.focusables[i].id.to_string());
+            }
+            Key::Activate => {
+                if let Some(i) = self.focus_index(screen) {
+                    let f = &screen.focusables[i];
+                    if f.clickable {
+                        dispatch_event(mw, &f.id, BUTTON_CLICK);
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+fn to_term_color(c: Color) -> style::Color {
+    style::Color::Rgb {
+        r: c.r,
+        g: c.g,
+        b: c.b,
+    }
+}
+
+fn draw(out: &mut impl Write, screen: &Screen, state: &TuiState) -> io::Result<()> {
+    let mut screen_cells = screen.cells.clone();
+    if let Some(i) = state.focus_index(screen) {
+        let mut s = Screen {
+            cols: screen.cols,
+            rows: screen.rows,
+            cells: screen_cells,
+            focusables: Vec::new(),
+        };
+        s.highlight(screen.focusables[i].rect);
+        screen_cells = s.cells;
+    }
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+    for (row, line) in screen_cells.chunks(screen.cols as usize).enumerate() {
+        queue!(out, cursor::MoveTo(0, row as u16))?;
+        for c in line.iter() {
+            queue!(out, SetAttribute(Attribute::Reset))?;
+            if let Some(fg) = c.fg {
+                queue!(out, SetForegroundColor(to_term_color(fg)))?;
+            }
+            if let Some(bg) = c.bg {
+                queue!(out, SetBackgroundColor(to_term_color(bg)))?;
+            }
+            if c.bold {
+                queue!(out, SetAttribute(Attribute::Bold))?;
+            }
+            if c.reverse {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(out, style::Print(c.ch))?;
+        }
+    }
+    queue!(out, SetAttribute(Attribute::Reset))?;
+    out.flush()
+}
+
+fn event_loop(out: &mut impl Write, mw: MainCallable) -> io::Result<()> {
+    let mut state = TuiState::new();
+    loop {
+        let (cols, rows) = terminal::size()?;
+        // handlers may change the tree, so it is drawn again every time
+        let screen = match mw.lock().get_tree() {
+            Some(tree) => render_screen(tree, cols, rows),
+            None => return Ok(()),
+        };
+        if state.focused.is_none() {
+            state.handle_key(Key::Next, &screen, mw);
+        }
+        draw(out, &screen, &state)?;
+
+        if let TermEvent::Key(k) = event::read()? {
+            if k.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(key) = key_of(&k) {
+                if !state.handle_key(key, &screen, mw) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// runs the tree of 'mw' in the terminal until Esc or q is pressed
+// leaves raw mode and the alternate screen when dropped, so the terminal
+// is usable again however the event loop ends
+type PanicHook = dyn Fn(&panic::PanicHookInfo<'_>) + Sync + Send + 'static;
+
+// raw mode and the alternate screen, undone when dropped. While it lives a
+// panicking handler's message would be lost on the alternate screen, so
+// the panic hook restores the terminal first
+struct TerminalGuard {
+    previous_hook: Arc<PanicHook>,
+}
+
+impl TerminalGuard {
+    fn enter(out: &mut impl Write) -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        let previous_hook: Arc<PanicHook> = Arc::from(panic::take_hook());
+        let hook = previous_hook.clone();
+        panic::set_hook(Box::new(move |info| {
+            TerminalGuard::restore();
+            hook(info);
+        }));
+        let guard = TerminalGuard { previous_hook };
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(guard)
+    }
+
+    fn restore() {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        TerminalGuard::restore();
+        // the hook can not be changed while unwinding, it has run by then
+        if !thread::panicking() {
+            let previous = self.previous_hook.clone();
+            panic::set_hook(Box::new(move |info| previous(info)));
+        }
+    }
+}
+
+pub fn run_tui(mw: MainCallable) -> io::Result<()> {
+    let mut out = io::stdout();
+    let _guard = TerminalGuard::enter(&mut out)?;
+    event_loop(&mut out, mw)
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use crate::callable::CallableByName;
+    use crate::events::RoutedEventArgs;
+    use crate::tui::{draw, render_screen, Key, TuiState};
+    use crate::ui_elements::{UIElementRef, UITreeRef};
+    use crate::xaml_reader;
+
+    struct Recorder {
+        tree: Option<UITreeRef>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl CallableByName for Recorder {
+        fn call_method(&mut self, name: &str, _sender: &UIElementRef, _args: &RoutedEventArgs) {
+            self.calls.lock().push(name.to_string());
+        }
+        fn get_tree(&self) -> Option<UITreeRef> {
+            self.tree
+        }
+        fn set_tree(&mut self, tree: Option<UITreeRef>) {
+            self.tree = tree;
+        }
+    }
+
+    #[test]
+    fn draws_frame_and_text() {
+        let text = "<Window Title=\"Hi\"><StackPanel>\
+                    <TextBlock Text=\"one\"/><Button Click=\"on_ok\">Ok</Button>\
+                    </StackPanel></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let screen = render_screen(&t, 12, 5);
+        assert_eq!(
+            screen.to_lines(),
+            vec![
+                "┌─ Hi ─────┐",
+                "│one       │",
+                "│   [Ok]   │",
+                "│          │",
+                "└──────────┘",
+            ]
+        );
+        assert_eq!(screen.focusables.len(), 2);
+        assert!(screen.focusables[1].clickable);
+    }
+
+    #[test]
+    fn keys_move_focus() {
+        let text = "<Window><StackPanel><Button>A</Button><Label>x</Label>\
+                    <Button Click=\"on_b\">B</Button></StackPanel></Window>";
+        let t: UITreeRef = Box::leak(Box::new(xaml_reader::read_xaml_str("inline", text).unwrap()));
+        let mw = Box::leak(Box::new(Mutex::new(Recorder {
+            tree: Some(t),
+            calls: Mutex::new(Vec::new()),
+        })));
+        let screen = render_screen(t, 10, 6);
+        let ids: Vec<String> = screen.focusables.iter().map(|f| f.id.to_string()).collect();
+
+        let mut state = TuiState::new();
+        assert!(state.handle_key(Key::Next, &screen, mw));
+        assert_eq!(state.focused.as_ref(), Some(&ids[0]));
+        state.handle_key(Key::Previous, &screen, mw);
+        assert_eq!(state.focused.as_ref(), Some(&ids[2]));
+        assert!(state.handle_key(Key::Activate, &screen, mw));
+        assert!(!state.handle_key(Key::Quit, &screen, mw));
+        assert_eq!(*mw.lock().calls.lock(), vec!["on_b".to_string()]);
+    }
+
+    #[test]
+    fn oversized_elements_are_cut_at_the_screen() {
+        let text = "<Window><Grid ShowGridLines=\"True\" Height=\"9000000\">\
+                    <TextBlock Width=\"600000\" Background=\"Red\" Text=\"x\"/>\
+                    <Button Margin=\"600000,0,0,0\" Width=\"900000\">Ok</Button>\
+                    </Grid></Window>";
+        let t: UITreeRef = Box::leak(Box::new(xaml_reader::read_xaml_str("inline", text).unwrap()));
+        let mw = Box::leak(Box::new(Mutex::new(Recorder {
+            tree: Some(t),
+            calls: Mutex::new(Vec::new()),
+        })));
+        let screen = render_screen(t, 10, 4);
+        assert_eq!(screen.to_lines()[1], "│x       │");
+
+        let mut state = TuiState::new();
+        state.handle_key(Key::Next, &screen, mw);
+        let mut out = Vec::new();
+        draw(&mut out, &screen, &state).unwrap();
+    }
+}
@@ -1,17 +1,25 @@
+use std::{cell::Cell, rc::Rc};
+
 use gtk::gio::ApplicationFlags;
 use gtk::{glib, prelude::*};
 use gtk::{Application, ApplicationWindow};
+use log::debug;
 
 use crate::callable::MainCallable;
-use crate::events::Event;
+use crate::converters::{GridLength, HorizontalAlignment, Thickness, VerticalAlignment};
+use crate::events::{dispatch_routed_event, RoutedEventArgs};
+use crate::logging::BUILDER;
 use crate::stack_layout::StackLayout;
-use crate::ui_elements::{UIElement, UIElementRef};
+use crate::properties::PropertyValue;
+use crate::routed_events::{BUTTON_CLICK, KEY_DOWN};
+use crate::ui_elements::{LayoutProps, UIElement, UITree};
 use crate::visitor::Visitor;
 use crate::{
     button::Button,
     content_page::ContentPage,
     grid_layout::{
-        ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition,
+        ColumnDefinition, GridColumnDefinitions, GridLayout, GridPlacement, GridRowDefinitions,
+        RowDefinition,
     },
     label::Label,
     text_block::TextBlock,
@@ -23,12 +31,21 @@ const APP_ID: &str = "org.gtk_rs.HelloWorld1";
 
 fn open_ui(app: &Application, main_win: MainCallable, files: &[gtk::gio::File], _s: &str) {
     for file in files {
-        println!("Open--: {:?}", file.path());
+        debug!(target: BUILDER, "opening {:?}", file.path());
     }
 
     let builder = build_ui_from_xaml(app, main_win);
     let window_opt = builder.root;
     if let Some(window) = window_opt {
+        if !builder.css.is_empty() {
+            let provider = gtk::CssProvider::new();
+            provider.load_from_data(&builder.css);
+            gtk::style_context_add_provider_for_display(
+                &WidgetExt::display(&window),
+                &provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        }
         window.present();
     } else {
         panic!("failed to build ui from XAML");
@@ -36,7 +53,7 @@ fn open_ui(app: &Application, main_win: MainCallable, files: &[gtk::gio::File],
 }
 
 fn build_ui(_app: &Application) {
-    println!("build-ui");
+    debug!(target: BUILDER, "build-ui");
     // Create a window and set the title
     /*let window = ApplicationWindow::builder()
         .application(app)
@@ -51,15 +68,135 @@ fn build_ui(_app: &Application) {
     // window.present();
 }
 
+#[derive(Clone)]
 enum GtkPushed {
     Button(gtk::Button),
     Label(gtk::Label),
+    Box(gtk::Box),
+    Grid(gtk::Grid),
+}
+
+impl GtkPushed {
+    fn widget(&self) -> &gtk::Widget {
+        match self {
+            GtkPushed::Button(b) => b.upcast_ref(),
+            GtkPushed::Label(l) => l.upcast_ref(),
+            GtkPushed::Box(b) => b.upcast_ref(),
+            GtkPushed::Grid(g) => g.upcast_ref(),
+        }
+    }
+
+    // follows a property of its element that changed after it was built
+    fn update(&self, name: &str, value: &PropertyValue) {
+        match (name, self) {
+            ("IsEnabled", _) => self.widget().set_sensitive(value.as_bool().unwrap_or(true)),
+            ("Visibility", _) => self.widget().set_visible(value.to_string() == "Visible"),
+            ("Content", GtkPushed::Button(b)) => b.set_label(&value.to_string()),
+            ("Text", GtkPushed::Label(l)) => l.set_text(&value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+struct Pushed {
+    gtk: GtkPushed,
+    placement: GridPlacement,
+}
+
+impl Pushed {
+    fn widget(&self) -> &gtk::Widget {
+        self.gtk.widget()
+    }
+}
+
+// star weights are made whole with denominators up to this one, finer
+// fractions are rounded to twelfths
+const MAX_STAR_DENOMINATOR: i32 = 12;
+
+fn gcd(a: i32, b: i32) -> i32 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+// the number of gtk tracks of every star weight, in the proportions of the
+// weights: '0.5*' and '1*' get 1 and 2, '2.5*' and '1.5*' get 5 and 3
+fn star_track_counts(weights: &[f64]) -> Vec<i32> {
+    let whole = |d: i32| weights.iter().all(|w| (w * d as f64 - (w * d as f64).round()).abs() < 1e-6);
+    let d = (1..=MAX_STAR_DENOMINATOR).find(|d| whole(*d)).unwrap_or(MAX_STAR_DENOMINATOR);
+    let counts: Vec<i32> = weights.iter().map(|w| ((w * d as f64).round() as i32).max(1)).collect();
+    let divisor = counts.iter().fold(0, |g, c| gcd(*c, g)).max(1);
+    counts.iter().map(|c| c / divisor).collect()
+}
+
+// GtkGrid cannot weigh its rows and columns, star definitions are therefore
+// mapped onto equally expanding gtk tracks in the proportions of their
+// weights, '2*' and '1*' onto two and one. Everything else gets one track
+struct GridTracks {
+    // first gtk track of every definition, plus the total at the end
+    starts: Vec<i32>,
+}
+
+impl GridTracks {
+    fn new(defs: &[GridLength]) -> GridTracks {
+        let weights: Vec<f64> = defs
+            .iter()
+            .filter_map(|d| match d {
+                GridLength::Star(w) => Some(*w),
+                _ => None,
+            })
+            .collect();
+        let mut star_counts = star_track_counts(&weights).into_iter();
+
+        let mut starts = vec![0];
+        let mut n = 0;
+        for d in defs.iter() {
+            n += match d {
+                GridLength::Star(_) => star_counts.next().unwrap_or(1),
+                _ => 1,
+            };
+            starts.push(n);
+        }
+        GridTracks { starts }
+    }
+
+    // gtk start track and span of definitions [index, index+span)
+    fn span(&self, index: usize, span: usize) -> (i32, i32) {
+        let last = self.starts.len() - 1;
+        let first = index.min(last - 1);
+        let end = (first + span).min(last);
+        (self.starts[first], self.starts[end] - self.starts[first])
+    }
+
+    // adds a zero sized widget per gtk track outside of the visible cells
+    // that gives the track its size or makes it expand
+    fn add_struts(&self, grid: &gtk::Grid, defs: &[GridLength], horizontal: bool) {
+        for (i, d) in defs.iter().enumerate() {
+            for track in self.starts[i]..self.starts[i + 1] {
+                let strut = gtk::Box::new(gtk::Orientation::Vertical, 0);
+                match d {
+                    GridLength::Pixel(px) if horizontal => strut.set_size_request(*px as i32, 0),
+                    GridLength::Pixel(px) => strut.set_size_request(0, *px as i32),
+                    GridLength::Star(_) if horizontal => strut.set_hexpand(true),
+                    GridLength::Star(_) => strut.set_vexpand(true),
+                    GridLength::Auto => {}
+                }
+                if horizontal {
+                    grid.attach(&strut, track, -1, 1, 1);
+                } else {
+                    grid.attach(&strut, -1, track, 1, 1);
+                }
+            }
+        }
+    }
 }
 
 struct UIBuilder<'b> {
     root: Option<ApplicationWindow>,
     app: &'b Application,
-    nested_gtk_items: Vec<Vec<GtkPushed>>,
+    nested_gtk_items: Vec<Vec<Pushed>>,
+    // style rules for widgets, selected by their widget name (the element id)
+    css: String,
+    // every widget built, by element id
+    widgets: Vec<(String, GtkPushed)>,
     _main_win: MainCallable,
 }
 
@@ -69,6 +206,8 @@ impl<'b> UIBuilder<'b> {
             root: Option::None,
             app,
             nested_gtk_items: Vec::new(),
+            css: String::new(),
+            widgets: Vec::new(),
             _main_win: win,
         }
     }
@@ -81,34 +220,82 @@ impl<'b> UIBuilder<'b> {
         self.nested_gtk_items.pop();
     }
 
-    fn last_scope(&mut self) -> &mut Vec<GtkPushed> {
-        return self.nested_gtk_items.last_mut().unwrap();
+    // hands out the widgets pushed by the children of the element being left
+    fn take_scope(&mut self) -> Vec<Pushed> {
+        self.nested_gtk_items.pop().unwrap()
     }
-}
 
-fn handle_event_from_gtk(mw: MainCallable, id: &String,
-    ev_name: &str)
-{
-    let mut handler: Option<UIElementRef> = Option::None;
+    fn last_scope(&mut self) -> &mut Vec<Pushed> {
+        self.nested_gtk_items.last_mut().unwrap()
+    }
 
-    {
-        let k = mw.lock();
-        if let Some(tree) = k.get_tree() {
-            handler = tree.find_by_id(id.to_string());
-        } else {
-            println!("failed to get tree?");
+    // hands the widget of 'elt' to its parent, call after leaving its scope
+    fn push(&mut self, elt: &dyn UIElement, gtk: GtkPushed) {
+        self.apply_layout(elt, gtk.widget());
+        self.widgets.push((elt.get_id(), gtk.clone()));
+        let placement = GridPlacement::from_element(elt);
+        self.last_scope().push(Pushed { gtk, placement });
+    }
+
+    // GTK has no maximum size, Max* only limits the requested size
+    fn apply_layout(&mut self, elt: &dyn UIElement, widget: &gtk::Widget) {
+        let layout = LayoutProps::from_element(elt);
+
+        let m = layout.margin;
+        widget.set_margin_start(m.left.round() as i32);
+        widget.set_margin_top(m.top.round() as i32);
+        widget.set_margin_end(m.right.round() as i32);
+        widget.set_margin_bottom(m.bottom.round() as i32);
+
+        widget.set_halign(match layout.horizontal_alignment {
+            HorizontalAlignment::Left => gtk::Align::Start,
+            HorizontalAlignment::Center => gtk::Align::Center,
+            HorizontalAlignment::Right => gtk::Align::End,
+            HorizontalAlignment::Stretch => gtk::Align::Fill,
+        });
+        widget.set_valign(match layout.vertical_alignment {
+            VerticalAlignment::Top => gtk::Align::Start,
+            VerticalAlignment::Center => gtk::Align::Center,
+            VerticalAlignment::Bottom => gtk::Align::End,
+            VerticalAlignment::Stretch => gtk::Align::Fill,
+        });
+
+        if let Some(v) = elt.get_value("IsEnabled") {
+            widget.set_sensitive(v.as_bool().unwrap_or(true));
+        }
+        if let Some(v) = elt.get_value("Visibility") {
+            widget.set_visible(v.to_string() == "Visible");
+        }
+
+        let size = |v: Option<f64>| v.map(|v| v.round() as i32).unwrap_or(-1);
+        widget.set_size_request(size(layout.requested_width()), size(layout.requested_height()));
+
+        let p = layout.padding;
+        if p != Thickness::uniform(0.0) {
+            let rules = format!(
+                "padding: {}px {}px {}px {}px;",
+                p.top, p.right, p.bottom, p.left
+            );
+            self.add_css(widget, &elt.get_id(), &rules);
         }
     }
 
-    if let Some(b) = handler {
-        let k = b.lock();
-        println!("elt found = {}", k.get_ui_type_name());
-        k.handle_event(Event::new(ev_name, mw));
-    } else {
-        println!("failed to find ui elt {}", id);
+    // updates the widgets when bindings or the code-behind change their
+    // elements, call once the tree was visited
+    fn connect_updates(&mut self, tree: &UITree) {
+        for (id, gtk) in self.widgets.drain(..) {
+            if let Some(elt) = tree.find_by_id(id) {
+                elt.lock()
+                    .add_property_changed_handler(Box::new(move |name, value| gtk.update(name, value)));
+            }
+        }
     }
-}
 
+    fn add_css(&mut self, widget: &gtk::Widget, id: &str, rules: &str) {
+        widget.set_widget_name(id);
+        self.css.push_str(&format!("#{} {{ {} }}\n", id, rules));
+    }
+}
 
 impl<'lifetime> Visitor for UIBuilder<'lifetime> {
     fn start_visit_button(&mut self, _b: &Button) {
@@ -152,12 +339,27 @@ impl<'lifetime> Visitor for UIBuilder<'lifetime> {
         let gtk_b = gtk::Button::with_label(&b.get_text());
         let mw = self._main_win;
         let id = b.get_id();
+
+        // 'clicked' has no pointer data, remember the last press
+        let press = Rc::new(Cell::new(None));
+        let gesture = gtk::GestureClick::new();
+        gesture.set_button(0);
+        gesture.set_propagation_phase(gtk::PropagationPhase::Capture);
+        let p = press.clone();
+        gesture.connect_pressed(move |g, _n, x, y| p.set(Some((x, y, g.current_button()))));
+        gtk_b.add_controller(gesture);
+
         gtk_b.connect_clicked(move |_gtk_button| {
-            handle_event_from_gtk(mw, &id, "Button.Click");
+            let mut args = RoutedEventArgs::new(BUTTON_CLICK, &id);
+            if let Some((x, y, button)) = press.take() {
+                args.position = Some((x, y));
+                args.button = Some(button);
+            }
+            dispatch_routed_event(mw, args);
         });
 
         self.leave_scope();
-        self.last_scope().push(GtkPushed::Button(gtk_b));
+        self.push(b, GtkPushed::Button(gtk_b));
     }
 
     fn visit_window(&mut self, w: &Window) {
@@ -168,42 +370,127 @@ impl<'lifetime> Visitor for UIBuilder<'lifetime> {
             .default_height(w.get_height())
             .build();
 
-        for elt in self.last_scope() {
-            match elt {
-                GtkPushed::Label(l) => {
-                    println!("adding label to window");
-                    win.set_child(Some(l));
-                }
-                GtkPushed::Button(l) => {
-                    println!("adding button to window");
-                    win.set_child(Some(l));
-                }
+        let keys = gtk::EventControllerKey::new();
+        let mw = self._main_win;
+        let id = w.get_id();
+        keys.connect_key_pressed(move |_, key, _, _| {
+            let mut args = RoutedEventArgs::new(KEY_DOWN, &id);
+            args.key = key.name().map(|n| n.to_string());
+            dispatch_routed_event(mw, args);
+            glib::Propagation::Proceed
+        });
+        win.add_controller(keys);
+
+        // a window holds a single child, stack them when there are more
+        let mut children = self.take_scope();
+        if children.len() == 1 {
+            let elt = children.pop().unwrap();
+            debug!(target: BUILDER, "adding {:?} to window", elt.widget().type_());
+            win.set_child(Some(elt.widget()));
+        } else if !children.is_empty() {
+            let gtk_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+            for elt in children.iter() {
+                gtk_box.append(elt.widget());
             }
+            win.set_child(Some(&gtk_box));
         }
 
         if self.root.is_none() {
             self.root = Option::Some(win);
         }
-        self.leave_scope();
     }
 
     fn visit_label(&mut self, l: &Label) {
         let gtk_label = gtk::Label::new(Option::Some(l.get_content().as_str()));
 
         self.leave_scope();
-        self.last_scope().push(GtkPushed::Label(gtk_label));
+        self.push(l, GtkPushed::Label(gtk_label));
     }
 
-    fn visit_text_block(&mut self, _t: &TextBlock) {
+    fn visit_text_block(&mut self, t: &TextBlock) {
+        let gtk_label = gtk::Label::new(Option::Some(t.get_text().as_str()));
+
+        gtk_label.set_wrap(t.wraps());
+        if t.trims() {
+            gtk_label.set_ellipsize(gtk::pango::EllipsizeMode::End);
+        }
+        let (justify, xalign) = match t.get_text_alignment().as_str() {
+            "Right" => (gtk::Justification::Right, 1.0),
+            "Center" => (gtk::Justification::Center, 0.5),
+            "Justify" => (gtk::Justification::Fill, 0.0),
+            _ => (gtk::Justification::Left, 0.0),
+        };
+        gtk_label.set_justify(justify);
+        gtk_label.set_xalign(xalign);
+
+        let mut rules = String::new();
+        if let Some(size) = t.get_font_size() {
+            rules.push_str(&format!("font-size: {}px;", size));
+        }
+        if let Some(weight) = t.get_font_weight() {
+            rules.push_str(&format!("font-weight: {};", weight));
+        }
+        if let Some(c) = t.get_foreground() {
+            rules.push_str(&format!("color: {};", c.to_css()));
+        }
+        if !rules.is_empty() {
+            self.add_css(gtk_label.upcast_ref(), &t.get_id(), &rules);
+        }
+
         self.leave_scope();
+        self.push(t, GtkPushed::Label(gtk_label));
     }
 
-    fn visit_grid(&mut self, _g: &GridLayout) {
-        self.leave_scope();
+    fn visit_grid(&mut self, g: &GridLayout) {
+        let rows = g.get_row_definitions();
+        let columns = g.get_column_definitions();
+        let row_tracks = GridTracks::new(&rows);
+        let column_tracks = GridTracks::new(&columns);
+
+        let grid = gtk::Grid::new();
+        row_tracks.add_struts(&grid, &rows, false);
+        column_tracks.add_struts(&grid, &columns, true);
+
+        let children = self.take_scope();
+        for elt in children.iter() {
+            let p = &elt.placement;
+            let (row, height) = row_tracks.span(p.row, p.row_span);
+            let (column, width) = column_tracks.span(p.column, p.column_span);
+            grid.attach(elt.widget(), column, row, width, height);
+        }
+
+        let mut rules = String::new();
+        if let Some(c) = g.get_background() {
+            rules.push_str(&format!("background-color: {};", c.to_css()));
+        }
+        if !rules.is_empty() {
+            self.add_css(grid.upcast_ref(), &g.get_id(), &rules);
+        }
+        if g.shows_grid_lines() {
+            for elt in children.iter() {
+                elt.widget().add_css_class("ramaui-grid-line");
+            }
+            if !self.css.contains(".ramaui-grid-line") {
+                self.css
+                    .push_str(".ramaui-grid-line { outline: 1px dashed #606060; outline-offset: -1px; }\n");
+            }
+        }
+
+        self.push(g, GtkPushed::Grid(grid));
     }
 
-    fn visit_stack(&mut self, _g: &StackLayout) {
-        self.leave_scope();
+    fn visit_stack(&mut self, g: &StackLayout) {
+        let orientation = if g.is_horizontal() {
+            gtk::Orientation::Horizontal
+        } else {
+            gtk::Orientation::Vertical
+        };
+        let gtk_box = gtk::Box::new(orientation, g.get_spacing().round() as i32);
+        for elt in self.take_scope().iter() {
+            gtk_box.append(elt.widget());
+        }
+
+        self.push(g, GtkPushed::Box(gtk_box));
     }
 
     fn visit_grid_cols(&mut self, _g: &GridColumnDefinitions) {
@@ -237,6 +524,7 @@ fn build_ui_from_xaml<'b>(app: &'b Application, main_win: MainCallable) -> UIBui
         if let Some(r) = main_win.lock().get_tree() {
             if let Some(k) = &r.root {
                 k.lock().visit(&mut builder);
+                builder.connect_updates(r);
             } else {
                 panic!("no tree-root in main win");
             }
@@ -270,3 +558,17 @@ pub fn start_interpreter(win: MainCallable) -> glib::ExitCode {
 
     panic!();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::ui_builder::star_track_counts;
+
+    #[test]
+    fn star_tracks_keep_the_weight_proportions() {
+        assert_eq!(star_track_counts(&[0.5, 1.0]), vec![1, 2]);
+        assert_eq!(star_track_counts(&[2.5, 1.5]), vec![5, 3]);
+        assert_eq!(star_track_counts(&[4.0, 2.0, 2.0]), vec![2, 1, 1]);
+        assert_eq!(star_track_counts(&[1.0, 0.0]), vec![1, 1]);
+        assert!(star_track_counts(&[]).is_empty());
+    }
+}
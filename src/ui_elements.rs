@@ -1,22 +1,115 @@
-use std::{i32, sync::Arc};
+use std::sync::Arc;
 
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{events::Event, visitor::Visitor};
+use crate::{
+    binding::{Binding, BindingEngine},
+    converters::{HorizontalAlignment, Thickness, VerticalAlignment},
+    properties::{PropertyChangedHandler, PropertyDef, PropertyStore, PropertyValue},
+    visitor::Visitor,
+};
 
 pub type UIElementRef = Arc<Mutex<dyn UIElement>>;
 
+// lookup tables for the elements of a tree, kept up to date by
+// UITree::add_child
+struct TreeIndex {
+    ids: HashMap<String, UIElementRef>,
+    // the name scope: elements declared with x:Name or Name
+    names: HashMap<String, UIElementRef>,
+}
+
 pub struct UITree {
     pub root: Option<UIElementRef>,
+    index: Mutex<TreeIndex>,
+    // the bindings of the elements once the tree is activated
+    pub bindings: Mutex<BindingEngine>,
 }
 
 impl UITree {
     pub fn new() -> UITree {
-        UITree { root: Option::None }
+        UITree {
+            root: Option::None,
+            index: Mutex::new(TreeIndex {
+                ids: HashMap::new(),
+                names: HashMap::new(),
+            }),
+            bindings: Mutex::new(BindingEngine::new()),
+        }
+    }
+
+    // adds a single element to the id and name index, returns false when its
+    // id or name is already taken in this tree
+    pub fn add_to_index(&self, elt: UIElementRef) -> bool {
+        let (id, name) = {
+            let k = elt.lock();
+            (k.get_id(), k.get_name())
+        };
+
+        let mut index = self.index.lock();
+        if index.ids.contains_key(&id) || name.as_ref().is_some_and(|n| index.names.contains_key(n)) {
+            return false;
+        }
+        if let Some(n) = name {
+            index.names.insert(n, elt.clone());
+        }
+        index.ids.insert(id, elt);
+        true
+    }
+
+    // adds 'child' and everything below it to 'parent', nothing is added
+    // when one of the new ids or names is already in use, in the tree or
+    // within the subtree itself
+    pub fn add_child(&self, parent: &UIElementRef, child: UIElementRef) -> bool {
+        let subtree: Vec<(String, Option<String>, UIElementRef)> = collect_subtree(&child)
+            .into_iter()
+            .map(|e| {
+                let (id, name) = {
+                    let k = e.lock();
+                    (k.get_id(), k.get_name())
+                };
+                (id, name, e)
+            })
+            .collect();
+
+        {
+            let mut index = self.index.lock();
+            let mut ids = HashSet::new();
+            let mut names = HashSet::new();
+            for (id, name, _) in subtree.iter() {
+                if index.ids.contains_key(id) || !ids.insert(id) {
+                    return false;
+                }
+                if let Some(n) = name {
+                    if index.names.contains_key(n) || !names.insert(n) {
+                        return false;
+                    }
+                }
+            }
+
+            for (id, name, e) in subtree.iter() {
+                if let Some(n) = name {
+                    index.names.insert(n.to_string(), e.clone());
+                }
+                index.ids.insert(id.to_string(), e.clone());
+            }
+        }
+        parent.lock().add_child(child, parent.clone());
+        true
+    }
+
+    #[allow(dead_code)]
+    pub fn find_by_name(&self, name: &str) -> Option<UIElementRef> {
+        self.index.lock().names.get(name).cloned()
     }
 
     pub fn find_by_id(&self, id: String) -> Option<UIElementRef> {
+        if let Some(x) = self.index.lock().ids.get(&id) {
+            return Some(x.clone());
+        }
+
+        // elements added without going through the tree are not indexed
         if let Some(x) = &self.root {
             let k = x.lock();
             if k.get_id() == id {
@@ -25,61 +118,188 @@ impl UITree {
 
             return k.find_by_id(id);
         }
-        return None;
+        None
+    }
+}
+
+// 'elt' followed by all of its descendants, depth first
+pub fn collect_subtree(elt: &UIElementRef) -> Vec<UIElementRef> {
+    let mut ret = vec![elt.clone()];
+    let children = elt.lock().get_children();
+    for c in children.iter() {
+        ret.extend(collect_subtree(c));
     }
+    ret
 }
 
 pub type UITreeRef = &'static UITree;
 
-// utility func for extracting props from XAML
-pub fn get_attribute(
-    attributes: &HashMap<String, String>,
-    name: &str,
-    default_str: &str,
-) -> String {
-    if let Some(ret) = attributes.get(name) {
-        return ret.to_string();
+// x:Name and Name are interchangeable, x:Name wins when both are given
+pub fn get_element_name(attributes: &HashMap<String, String>) -> Option<String> {
+    if let Some(n) = attributes.get("x:Name") {
+        return Some(n.to_string());
+    }
+    attributes.get("Name").map(|n| n.to_string())
+}
+
+// the layout properties every element understands, all lengths are in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutProps {
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub min_width: f64,
+    pub max_width: f64,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub margin: Thickness,
+    pub padding: Thickness,
+    pub horizontal_alignment: HorizontalAlignment,
+    pub vertical_alignment: VerticalAlignment,
+}
+
+impl LayoutProps {
+    pub fn from_element(elt: &dyn UIElement) -> LayoutProps {
+        LayoutProps::from_values(|name| elt.get_value(name))
+    }
+
+    fn from_values(get: impl Fn(&str) -> Option<PropertyValue>) -> LayoutProps {
+        let length = |name: &str| get(name).and_then(|v| v.as_f64());
+        let thickness = |name: &str| {
+            get(name)
+                .and_then(|v| v.as_thickness())
+                .unwrap_or(Thickness::uniform(0.0))
+        };
+        LayoutProps {
+            width: length("Width"),
+            height: length("Height"),
+            min_width: length("MinWidth").unwrap_or(0.0),
+            max_width: length("MaxWidth").unwrap_or(f64::INFINITY),
+            min_height: length("MinHeight").unwrap_or(0.0),
+            max_height: length("MaxHeight").unwrap_or(f64::INFINITY),
+            margin: thickness("Margin"),
+            padding: thickness("Padding"),
+            horizontal_alignment: get("HorizontalAlignment")
+                .and_then(|v| HorizontalAlignment::parse(&v.to_string()).ok())
+                .unwrap_or(HorizontalAlignment::Stretch),
+            vertical_alignment: get("VerticalAlignment")
+                .and_then(|v| VerticalAlignment::parse(&v.to_string()).ok())
+                .unwrap_or(VerticalAlignment::Stretch),
+        }
+    }
+
+    // Width, or else the smallest allowed width, kept within Min/MaxWidth
+    pub fn requested_width(&self) -> Option<f64> {
+        clamp_length(self.width, self.min_width, self.max_width)
+    }
+
+    pub fn requested_height(&self) -> Option<f64> {
+        clamp_length(self.height, self.min_height, self.max_height)
     }
+}
+
+fn clamp_length(v: Option<f64>, min: f64, max: f64) -> Option<f64> {
+    match v {
+        Some(v) => Some(v.min(max).max(min)),
+        None if min > 0.0 => Some(min),
+        None => None,
+    }
+}
 
-    default_str.to_string()
+// how an element was spelled in its document, so a tree can be written
+// back the way it was read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XamlMarkup {
+    // the tag with its prefix, empty for elements created in code
+    pub tag: String,
+    // the xmlns declarations on the element as (prefix, uri), the prefix is
+    // empty for the default namespace
+    pub namespaces: Vec<(String, String)>,
+    // the attribute keys in document order
+    pub attributes: Vec<String>,
+    // the property the text content of the element was stored in
+    pub content_property: Option<String>,
+    // the properties given as '{Binding ...}', by attribute key
+    pub bindings: Vec<(String, Binding)>,
 }
 
 pub trait UIAlloc {
     fn new(attributes: &HashMap<String, String>, id: String) -> Self;
+
+    // the typed properties the element understands besides the common ones,
+    // used to validate attribute values while parsing
+    fn properties() -> &'static [PropertyDef] {
+        &[]
+    }
 }
 
 pub trait UIElement {
     fn get_id(&self) -> String;
+    fn get_name(&self) -> Option<String>;
     fn find_by_id(&self, id: String) -> Option<UIElementRef>;
 
     fn get_ui_type_name(&self) -> &'static str;
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef);
+    fn get_children(&self) -> Vec<UIElementRef>;
     fn set_parent(&mut self, parent: UIElementRef);
     fn dump(&self, indent: i32);
     fn add_content_string(&mut self, s: String);
-    fn get_attribute(&self, s: &str) -> Option<&String>;
+    fn get_value(&self, name: &str) -> Option<PropertyValue>;
+    #[allow(dead_code)]
+    fn set_value(&mut self, name: &str, value: PropertyValue);
+    // parses 'value' according to the property definition
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String>;
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler);
+    // the explicitly set values, in the order they were first set
+    fn get_values(&self) -> Vec<(String, PropertyValue)>;
+    fn get_markup(&self) -> XamlMarkup;
+    fn set_markup(&mut self, markup: XamlMarkup);
 
     fn visit(&self, visitor: &mut dyn Visitor);
 
-    fn handle_event(&self, ev: Event);
+    // the code-behind method handling 'event_name' on this element
+    fn get_handler_name(&self, event_name: &str) -> Option<String>;
+    fn get_parent(&self) -> Option<UIElementRef>;
 }
 
 pub struct UICommon {
     parent: Option<UIElementRef>,
-    attributes: HashMap<String, String>,
+    properties: PropertyStore,
     children: Vec<UIElementRef>,
     id: String,
+    name: Option<String>,
     parent_type: String,
+    markup: XamlMarkup,
 }
 
 impl UICommon {
-    pub fn new(attributes: &HashMap<String, String>, parent_type: &str, id: String) -> UICommon {
+    pub fn new(
+        attributes: &HashMap<String, String>,
+        parent_type: &str,
+        id: String,
+        defs: &'static [PropertyDef],
+    ) -> UICommon {
+        let mut properties = PropertyStore::new(defs);
+
+        // sorted so the order of the set values does not depend on hashing
+        let mut keys: Vec<&String> = attributes.keys().collect();
+        keys.sort();
+        for k in keys {
+            let v = &attributes[k];
+            // the reader validated the value already, keep the text if it
+            // was created some other way
+            if properties.set_str(k, v).is_err() {
+                properties.set(k, PropertyValue::Str(v.to_string()));
+            }
+        }
+
         UICommon {
             parent: Option::None,
-            attributes: attributes.clone(),
+            properties,
             children: Vec::new(),
             id,
+            name: get_element_name(attributes),
             parent_type: parent_type.to_string(),
+            markup: XamlMarkup::default(),
         }
     }
 
@@ -87,81 +307,115 @@ impl UICommon {
         self.id.to_string()
     }
 
+    pub fn get_name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    // searches all descendants, not just the direct children
     pub fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         for c in self.children.iter() {
-            let k = c.clone();
-            if k.lock().get_id() == id {
-                return Some(k);
+            let k = c.lock();
+            if k.get_id() == id {
+                return Some(c.clone());
+            }
+            if let Some(found) = k.find_by_id(id.to_string()) {
+                return Some(found);
             }
         }
 
-        return None;
+        None
     }
 
     pub fn set_parent(&mut self, parent: UIElementRef) {
         self.parent = Some(parent);
     }
 
-    pub fn handle_event(&self, ev: Event) {
-        if let Some(p) = self.internal_get_attribute(&ev.get_name()) {
-            let mc = ev.get_callable();
-            mc.lock().call_method(p.as_str());
-            return;
+    // the handler for a 'Button.Click' event is given as Click="..." on a
+    // Button itself or as Button.Click="..." on any element it bubbles through
+    pub fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        if let Some(PropertyValue::Str(h)) = self.properties.get(event_name) {
+            return Some(h);
         }
-
-        if let Some(p) = &self.parent {
-            p.lock().handle_event(ev);
+        let (owner, event) = event_name.split_once('.')?;
+        if owner != self.parent_type {
+            return None;
+        }
+        match self.properties.get(event) {
+            Some(PropertyValue::Str(h)) => Some(h),
+            _ => None,
         }
     }
 
-    fn get_prop_name(&self, prop: &str) -> String {
-        format!("{}.{}", self.parent_type.to_string(), prop)
+    pub fn get_parent(&self) -> Option<UIElementRef> {
+        self.parent.clone()
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.properties.get(name)
+    }
+
+    pub fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.properties.set(name, value);
+    }
+
+    // parses 'value' according to the property definition
+    pub fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.properties.set_str(name, value)
     }
 
-    pub fn get_attr_opt(&self, prop_name: &str) -> Option<&String> {
-        let pn = self.get_prop_name(prop_name);
-        self.internal_get_attribute(pn.as_str())
+    pub fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.properties.iter().cloned().collect()
     }
 
-    pub fn get_attr(&self, prop_name: &str) -> String {
-        let x = self.get_attr_opt(prop_name);
-        if x.is_none() {
-            return "".to_string();
+    pub fn get_markup(&self) -> XamlMarkup {
+        self.markup.clone()
+    }
+
+    pub fn set_markup(&mut self, markup: XamlMarkup) {
+        self.markup = markup;
+    }
+
+    // stores the text content of the element in 'property'
+    pub fn set_content(&mut self, property: &str, s: &str) {
+        self.set_str(property, s);
+        self.markup.content_property = Some(property.to_string());
+    }
+
+    pub fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.properties.add_changed_handler(handler);
+    }
+
+    // empty when the property has no value
+    pub fn get_str(&self, name: &str) -> String {
+        match self.properties.get(name) {
+            Some(v) => v.to_string(),
+            None => "".to_string(),
         }
-        x.unwrap().to_string()
     }
 
-    pub fn set_attr(&mut self, prop: &str, value: String) {
-        let pn = self.get_prop_name(prop);
-        self.internal_set_attribute(pn.as_str(), &value)
+    pub fn get_i32(&self, name: &str) -> Option<i32> {
+        self.properties.get(name).and_then(|v| v.as_i32())
     }
 
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        self.properties.get(name).and_then(|v| v.as_f64())
+    }
 
-    pub fn set_width(&mut self, v: i32) {
-        self.set_attr("Width", v.to_string());
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.properties.get(name).and_then(|v| v.as_bool())
     }
 
-    pub fn set_height(&mut self, v: i32) {
-        self.set_attr("Height", v.to_string());
+    pub fn set_str(&mut self, name: &str, value: &str) {
+        self.set_value(name, PropertyValue::Str(value.to_string()));
     }
 
+    // None when not set or 'Auto'
     pub fn get_width(&self) -> Option<i32> {
-        let pn = self.get_prop_name("Width");
-        let value = get_attribute(&self.attributes, pn.as_str(), "").parse::<i32>();
-        if value.is_err() {
-            return Option::None;
-        }
-        Option::Some(value.unwrap())
+        self.get_i32("Width")
     }
 
     pub fn get_height(&self) -> Option<i32> {
-        let pn = self.get_prop_name("Height");
-
-        let value = get_attribute(&self.attributes, pn.as_str(), "").parse::<i32>();
-        if value.is_err() {
-            return Option::None;
-        }
-        Option::Some(value.unwrap())
+        self.get_i32("Height")
     }
 
     pub fn visit(&self, visitor: &mut dyn Visitor) {
@@ -171,20 +425,15 @@ impl UICommon {
         }
     }
 
-    fn internal_get_attribute(&self, s: &str) -> Option<&String> {
-        return self.attributes.get(s);
-    }
-
-    fn internal_set_attribute(&mut self, property_name: &str, new_value: &String) {
-        self.attributes
-            .insert(property_name.to_string(), new_value.to_string());
-    }
-
     pub fn add_child(&mut self, child: UIElementRef, me: UIElementRef) {
         self.children.push(child.clone());
         child.lock().set_parent(me.clone());
     }
 
+    pub fn get_children(&self) -> Vec<UIElementRef> {
+        self.children.clone()
+    }
+
     pub fn dump(&self, _indent: i32) {
         let indent = 1 + _indent;
         for c in self.children.iter() {
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{events::Event, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef}, visitor::Visitor};
+use crate::{properties::{PropertyChangedHandler, PropertyValue}, ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup}, visitor::Visitor};
 
 
 pub struct Unknown {
@@ -11,7 +11,7 @@ pub struct Unknown {
 impl UIAlloc for Unknown {
     fn new(attributes: &HashMap<String, String>, id: String) -> Unknown {
         Unknown {
-            common: UICommon::new(attributes, "Unknown", id),
+            common: UICommon::new(attributes, "Unknown", id, &[]),
         }
     }
 }
@@ -20,12 +20,19 @@ impl UIElement for Unknown {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
 
@@ -33,8 +40,32 @@ impl UIElement for Unknown {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(&s.to_string())
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
     fn get_ui_type_name(&self) -> &'static str {
         "UnknownElementType"
@@ -42,11 +73,17 @@ impl UIElement for Unknown {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent)
     }
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!("{}DUMP: {}", tabs(indent), self.get_ui_type_name());
         self.common.dump(indent);
     }
-    fn add_content_string(&mut self, _: String) {}
+    // kept so the text survives writing the document back
+    fn add_content_string(&mut self, s: String) {
+        self.common.set_content("Content", &s);
+    }
     
     fn visit(&self, visitor: &mut dyn Visitor) {
         visitor.start_visit_unknown(self);
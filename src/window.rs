@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use crate::{
-    events::Event,
-    ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef},
+    properties::{PropertyChangedHandler, PropertyDef, PropertyKind, PropertyValue},
+    ui_elements::{tabs, UIAlloc, UICommon, UIElement, UIElementRef, XamlMarkup},
     visitor::Visitor,
 };
 
@@ -10,42 +10,43 @@ pub struct Window {
     common: UICommon,
 }
 
+static WINDOW_PROPERTIES: [PropertyDef; 4] = [
+    PropertyDef::unset("Title", PropertyKind::Str),
+    PropertyDef::new(
+        "WindowStyle",
+        PropertyKind::Enum(&["None", "SingleBorderWindow", "ThreeDBorderWindow", "ToolWindow"]),
+        "SingleBorderWindow",
+    ),
+    // windows get a usable size when the document leaves it out
+    PropertyDef::new("Width", PropertyKind::Length, "100"),
+    PropertyDef::new("Height", PropertyKind::Length, "100"),
+];
+
 impl UIAlloc for Window {
     fn new(attributes: &HashMap<String, String>, id: String) -> Window {
-        let mut w = Window {
-            common: UICommon::new(&attributes, "Window", id),
-        };
-
-        if w.common.get_width().is_none() {
-            w.set_width(100);
+        Window {
+            common: UICommon::new(attributes, "Window", id, &WINDOW_PROPERTIES),
         }
+    }
 
-        if w.common.get_height().is_none() {
-            w.set_height(100);
-        }
-        w
+    fn properties() -> &'static [PropertyDef] {
+        &WINDOW_PROPERTIES
     }
 }
 
 impl Window {
     pub fn get_title(&self) -> String {
-        return self.common.get_attr("Title");
+        self.common.get_str("Title")
     }
     pub fn get_window_style(&self) -> String {
-        return self.common.get_attr("WindowStyle");
+        self.common.get_str("WindowStyle")
     }
 
     pub fn get_width(&self) -> i32 {
-        self.common.get_width().unwrap()
+        self.common.get_width().unwrap_or(100)
     }
     pub fn get_height(&self) -> i32 {
-        self.common.get_height().unwrap()
-    }
-    pub fn set_width(&mut self, width: i32) {
-        self.common.set_width(width);
-    }
-    pub fn set_height(&mut self, height: i32) {
-        self.common.set_height(height);
+        self.common.get_height().unwrap_or(100)
     }
 }
 
@@ -53,21 +54,51 @@ impl UIElement for Window {
     fn get_id(&self) -> String {
         self.common.get_id()
     }
+    fn get_name(&self) -> Option<String> {
+        self.common.get_name()
+    }
     fn find_by_id(&self, id: String) -> Option<UIElementRef> {
         self.common.find_by_id(id)
     }
 
-    fn handle_event(&self, ev: Event) {
-        println!("NOTICE: window-handle-event");
-        self.common.handle_event(ev);
+    fn get_handler_name(&self, event_name: &str) -> Option<String> {
+        self.common.get_handler_name(event_name)
+    }
+
+    fn get_parent(&self) -> Option<UIElementRef> {
+        self.common.get_parent()
     }
 
     fn set_parent(&mut self, parent: UIElementRef) {
         self.common.set_parent(parent);
     }
 
-    fn get_attribute(&self, s: &str) -> Option<&String> {
-        self.common.get_attr_opt(s)
+    fn get_value(&self, name: &str) -> Option<PropertyValue> {
+        self.common.get_value(name)
+    }
+
+    fn set_value(&mut self, name: &str, value: PropertyValue) {
+        self.common.set_value(name, value);
+    }
+
+    fn set_value_str(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.common.set_value_str(name, value)
+    }
+
+    fn add_property_changed_handler(&mut self, handler: PropertyChangedHandler) {
+        self.common.add_property_changed_handler(handler);
+    }
+
+    fn get_values(&self) -> Vec<(String, PropertyValue)> {
+        self.common.get_values()
+    }
+
+    fn get_markup(&self) -> XamlMarkup {
+        self.common.get_markup()
+    }
+
+    fn set_markup(&mut self, markup: XamlMarkup) {
+        self.common.set_markup(markup);
     }
 
     fn get_ui_type_name(&self) -> &'static str {
@@ -77,6 +108,11 @@ impl UIElement for Window {
     fn add_child(&mut self, child: UIElementRef, parent: UIElementRef) {
         self.common.add_child(child, parent);
     }
+
+
+    fn get_children(&self) -> Vec<UIElementRef> {
+        self.common.get_children()
+    }
     fn dump(&self, indent: i32) {
         println!(
             "{}DUMP: {}  -  title:{}, style: {}",
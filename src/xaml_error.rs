@@ -0,0 +1,121 @@
+use std::fmt;
+
+use xml::common::TextPosition;
+
+// location of a problem in a XAML source, line and column are 1-based
+// so editors can jump straight to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcePos {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl SourcePos {
+    pub fn new(file: &str, pos: TextPosition) -> SourcePos {
+        SourcePos {
+            file: file.to_string(),
+            line: pos.row as u32 + 1,
+            column: pos.column as u32 + 1,
+        }
+    }
+}
+
+impl fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum XamlError {
+    Io {
+        file: String,
+        error: std::io::Error,
+    },
+    Syntax {
+        pos: SourcePos,
+        message: String,
+    },
+    Unbalanced {
+        pos: SourcePos,
+        element: String,
+    },
+    UnknownElement {
+        pos: SourcePos,
+        element: String,
+    },
+    UnboundPrefix {
+        pos: SourcePos,
+        prefix: String,
+    },
+    DuplicateName {
+        pos: SourcePos,
+        name: String,
+        first: SourcePos,
+    },
+    InvalidAttribute {
+        pos: SourcePos,
+        // qualified as 'Element.Attribute'
+        property: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl XamlError {
+    #[allow(dead_code)]
+    pub fn get_pos(&self) -> Option<&SourcePos> {
+        match self {
+            XamlError::Io { .. } => None,
+            XamlError::Syntax { pos, .. }
+            | XamlError::Unbalanced { pos, .. }
+            | XamlError::UnknownElement { pos, .. }
+            | XamlError::UnboundPrefix { pos, .. }
+            | XamlError::DuplicateName { pos, .. }
+            | XamlError::InvalidAttribute { pos, .. } => Some(pos),
+        }
+    }
+}
+
+impl fmt::Display for XamlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XamlError::Io { file, error } => write!(f, "{}: {}", file, error),
+            XamlError::Syntax { pos, message } => write!(f, "{}: syntax error: {}", pos, message),
+            XamlError::Unbalanced { pos, element } => {
+                write!(f, "{}: unbalanced element <{}>", pos, element)
+            }
+            XamlError::UnknownElement { pos, element } => {
+                write!(f, "{}: unknown element <{}>", pos, element)
+            }
+            XamlError::UnboundPrefix { pos, prefix } => {
+                write!(f, "{}: namespace prefix '{}' is not bound", pos, prefix)
+            }
+            XamlError::DuplicateName { pos, name, first } => write!(
+                f,
+                "{}: name '{}' is already used by the element at {}",
+                pos, name, first
+            ),
+            XamlError::InvalidAttribute {
+                pos,
+                property,
+                value,
+                reason,
+            } => write!(
+                f,
+                "{}: invalid value \"{}\" for {}: {}",
+                pos, value, property, reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for XamlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XamlError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs;
+
+use xml::namespace::Namespace;
+use xml::reader::{ParserConfig, XmlEvent};
+
+use crate::{
+    xaml_error::XamlError,
+    xaml_reader::{attribute_key, declared_namespaces, qualified_name, syntax_error},
+    xaml_writer::{attribute_rank, escape_text, start_tag},
+};
+
+// 'ramaui fmt': reformats documents at the XML level, so attribute values,
+// comments and unknown elements come out exactly as they went in
+
+pub const DEFAULT_WIDTH: usize = 100;
+const INDENT: &str = "    ";
+
+struct Element {
+    tag: String,
+    // (name as written, key for ordering, value)
+    attributes: Vec<(String, String, String)>,
+    children: Vec<Node>,
+}
+
+enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+    ProcessingInstruction(String),
+    // one or more empty lines between two nodes
+    Blank,
+}
+
+fn parse(source_name: &str, text: &str) -> Result<(Option<String>, Vec<Node>), XamlError> {
+    let parser = ParserConfig::new()
+        .ignore_comments(false)
+        .cdata_to_characters(true)
+        .create_reader(text.as_bytes());
+
+    let mut declaration = None;
+    let mut top: Vec<Node> = Vec::new();
+    let mut open: Vec<(Element, Namespace)> = Vec::new();
+
+    for e in parser {
+        let e = e.map_err(|e| syntax_error(source_name, &e))?;
+        let node = match e {
+            XmlEvent::StartDocument {
+                version, encoding, ..
+            } => {
+                if text.trim_start().starts_with("<?xml") {
+                    declaration = Some(format!("<?xml version=\"{}\" encoding=\"{}\"?>", version, encoding));
+                }
+                continue;
+            }
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let mut all = Vec::new();
+                for (prefix, uri) in declared_namespaces(&namespace, open.last().map(|(_, ns)| ns)) {
+                    let n = if prefix.is_empty() {
+                        "xmlns".to_string()
+                    } else {
+                        format!("xmlns:{}", prefix)
+                    };
+                    all.push((n.to_string(), n, uri));
+                }
+                for a in attributes.iter() {
+                    all.push((qualified_name(&a.name), attribute_key(&a.name), a.value.to_string()));
+                }
+                // a stable sort keeps the document order within a rank
+                all.sort_by_key(|(_, key, _)| attribute_rank(key));
+                let elt = Element {
+                    tag: qualified_name(&name),
+                    attributes: all,
+                    children: Vec::new(),
+                };
+                open.push((elt, namespace));
+                continue;
+            }
+            XmlEvent::EndElement { .. } => match open.pop() {
+                Some((elt, _)) => Node::Element(elt),
+                None => continue,
+            },
+            XmlEvent::Characters(s) => {
+                if s.trim().is_empty() {
+                    continue;
+                }
+                Node::Text(s.trim().to_string())
+            }
+            XmlEvent::Whitespace(s) => {
+                if s.matches('\n').count() < 2 {
+                    continue;
+                }
+                Node::Blank
+            }
+            XmlEvent::Comment(s) => Node::Comment(s),
+            XmlEvent::ProcessingInstruction { name, data } => match data {
+                Some(d) => Node::ProcessingInstruction(format!("<?{} {}?>", name, d)),
+                None => Node::ProcessingInstruction(format!("<?{}?>", name)),
+            },
+            _ => continue,
+        };
+        match open.last_mut() {
+            Some((parent, _)) => parent.children.push(node),
+            None => top.push(node),
+        }
+    }
+    Ok((declaration, top))
+}
+
+fn write_node(node: &Node, depth: usize, width: usize, out: &mut Vec<String>) {
+    let pad = INDENT.repeat(depth);
+    match node {
+        Node::Element(elt) => write_element(elt, depth, width, out),
+        Node::Text(t) => {
+            for line in escape_text(t).lines() {
+                out.push(format!("{}{}", pad, line.trim()));
+            }
+        }
+        Node::Comment(c) => out.push(format!("{}<!--{}-->", pad, c)),
+        Node::ProcessingInstruction(p) => out.push(format!("{}{}", pad, p)),
+        Node::Blank => out.push(String::new()),
+    }
+}
+
+fn write_element(elt: &Element, depth: usize, width: usize, out: &mut Vec<String>) {
+    let pad = INDENT.repeat(depth);
+    let attributes: Vec<(String, String)> = elt
+        .attributes
+        .iter()
+        .map(|(n, _, v)| (n.to_string(), v.to_string()))
+        .collect();
+
+    // no empty lines at the start or the end of an element
+    let mut children: &[Node] = &elt.children;
+    while let Some(Node::Blank) = children.first() {
+        children = &children[1..];
+    }
+    while let Some(Node::Blank) = children.last() {
+        children = &children[..children.len() - 1];
+    }
+
+    match children {
+        [] => out.push(start_tag(&pad, INDENT, &elt.tag, &attributes, " />", Some(width))),
+        // single line text stays on the line of the element
+        [Node::Text(t)] if !t.contains('\n') => {
+            let tag = start_tag(&pad, INDENT, &elt.tag, &attributes, ">", Some(width));
+            out.push(format!("{}{}</{}>", tag, escape_text(t), elt.tag));
+        }
+        _ => {
+            out.push(start_tag(&pad, INDENT, &elt.tag, &attributes, ">", Some(width)));
+            let mut last_blank = false;
+            for c in children.iter() {
+                let blank = matches!(c, Node::Blank);
+                if !(blank && last_blank) {
+                    write_node(c, depth + 1, width, out);
+                }
+                last_blank = blank;
+            }
+            out.push(format!("{}</{}>", pad, elt.tag));
+        }
+    }
+}
+
+// the canonical form of a document, start tags wider than 'width' columns
+// get one attribute per line
+pub fn format_xaml(source_name: &str, text: &str, width: usize) -> Result<String, XamlError> {
+    let (declaration, nodes) = parse(source_name, text)?;
+    let mut lines = Vec::new();
+    if let Some(d) = declaration {
+        lines.push(d);
+    }
+    for n in nodes.iter().filter(|n| !matches!(n, Node::Blank)) {
+        write_node(n, 0, width, &mut lines);
+    }
+
+    let mut ret = String::new();
+    for l in lines.iter() {
+        ret.push_str(l.trim_end());
+        ret.push('\n');
+    }
+    Ok(ret)
+}
+
+// rewrites 'path' when it is not formatted, with 'check' it is only
+// compared, returns whether it was formatted already
+pub fn format_file(path: &str, width: usize, check: bool) -> Result<bool, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let formatted = format_xaml(path, &text, width).map_err(|e| e.to_string())?;
+    if formatted == text {
+        return Ok(true);
+    }
+    if !check {
+        fs::write(path, formatted).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xaml_format::{format_xaml, DEFAULT_WIDTH};
+
+    #[test]
+    fn formats_documents() {
+        let text = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                    <Window Title=\"T\"  Width=\"450\" xmlns=\"http://schemas.microsoft.com/winfx/2006/xaml/presentation\"   \n\
+                    xmlns:w=\"http://schemas.microsoft.com/winfx/2006/xaml\">\n\n\n\
+                    <!-- the form -->\n\
+                    <StackPanel><Button Click=\"ok\" w:Name=\"B\" Margin=\"4\">  Ok </Button>  \n\n\
+                    <TextBlock Text=\"a &amp; b\"  /></StackPanel>\n\n</Window>  ";
+        let formatted = format_xaml("inline", text, DEFAULT_WIDTH).unwrap();
+        assert_eq!(
+            formatted,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <Window\n\
+             \x20   xmlns=\"http://schemas.microsoft.com/winfx/2006/xaml/presentation\"\n\
+             \x20   xmlns:w=\"http://schemas.microsoft.com/winfx/2006/xaml\"\n\
+             \x20   Width=\"450\"\n\
+             \x20   Title=\"T\">\n\
+             \x20   <!-- the form -->\n\
+             \x20   <StackPanel>\n\
+             \x20       <Button w:Name=\"B\" Margin=\"4\" Click=\"ok\">Ok</Button>\n\
+             \n\
+             \x20       <TextBlock Text=\"a &amp; b\" />\n\
+             \x20   </StackPanel>\n\
+             </Window>\n"
+        );
+        assert_eq!(format_xaml("inline", &formatted, DEFAULT_WIDTH).unwrap(), formatted);
+
+        let narrow = format_xaml("inline", &formatted, 40).unwrap();
+        assert!(narrow.contains("\n        <Button\n            w:Name=\"B\"\n            Margin=\"4\"\n            Click=\"ok\">Ok</Button>\n"));
+    }
+}
@@ -1,130 +1,537 @@
 use std::{collections::HashMap, fs::File};
-use std::io::BufReader;
-use parking_lot::Mutex;
-use std::sync::Arc;
+use std::io::{BufReader, Read};
+use log::{debug, trace};
+use xml::common::Position;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
 use xml::reader::{EventReader, XmlEvent};
 
-use crate::{button::Button, content_page::ContentPage, grid_layout::{ColumnDefinition, GridColumnDefinitions, GridLayout, GridRowDefinitions, RowDefinition}, label::Label, stack_layout::StackLayout, text_block::TextBlock, ui_elements::{UIAlloc, UIElement, UIElementRef}, unknown_ui_elt::Unknown, window::Window};
+use crate::{binding::Binding, element_registry::{static_leaker, ElementRegistry}, logging::PARSER, markup_extension::{is_markup_extension, unescape_literal, MarkupExtension}, namespaces::{unbound_prefix_from_message, XamlNamespace}, properties::parse_property, ui_elements::{get_element_name, UIElementRef, UITree, XamlMarkup}, unknown_ui_elt::Unknown, xaml_error::{SourcePos, XamlError}};
 
-fn static_leaker<T: UIElement + UIAlloc + 'static>(attributes: &HashMap<String, String>, id: String) -> UIElementRef
-{
-    let inner = T::new(&attributes, id);
-    let outer = Arc::new(Mutex::new(inner));
-    return outer;
+pub fn qualified_name(name: &OwnedName) -> String {
+    match &name.prefix {
+        Some(prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.to_string(),
+    }
 }
 
-fn create_ui_element(name: &str, raw_attributes: Vec<xml::attribute::OwnedAttribute>, id: String) -> UIElementRef {
-    let mut attributes = HashMap::<String, String>::new();
-
-    for x in raw_attributes {
-        let k = format!("{}.{}", name, x.name);
-        attributes.insert(k, x.value);
+// unprefixed attributes are stored by their local name, language directives
+// as 'x:Name' whatever prefix the document bound to the XAML namespace
+pub fn attribute_key(name: &OwnedName) -> String {
+    match &name.prefix {
+        None => name.local_name.to_string(),
+        Some(prefix) => match XamlNamespace::resolve(name.namespace.as_deref()) {
+            XamlNamespace::Language => format!("x:{}", name.local_name),
+            _ => format!("{}:{}", prefix, name.local_name),
+        },
     }
+}
 
-    match name {
-        "Label" => static_leaker::<Label>(&attributes, id),
-        "ContentPage" => static_leaker::<ContentPage>(&attributes, id),
-        "Button" =>  static_leaker::<Button>(&attributes, id),
-        "Window" => static_leaker::<Window>(&attributes, id),
-        "Grid" => static_leaker::<GridLayout>(&attributes, id),
-        "StackPanel" => static_leaker::<StackLayout>(&attributes, id),
-        "Grid.ColumnDefinitions" =>  static_leaker::<GridColumnDefinitions>(&attributes, id),
-        "Grid.RowDefinitions" => static_leaker::<GridRowDefinitions>(&attributes, id),
-        "ColumnDefinition" => static_leaker::<ColumnDefinition>(&attributes, id),
-        "RowDefinition" => static_leaker::<RowDefinition>(&attributes, id),
-        "TextBlock" => static_leaker::<TextBlock>(&attributes, id),
-        _ => static_leaker::<Unknown>(&attributes, id),
-    }
+fn attributes_by_key(raw_attributes: &[xml::attribute::OwnedAttribute]) -> HashMap<String, String> {
+    raw_attributes
+        .iter()
+        .map(|a| (attribute_key(&a.name), a.value.to_string()))
+        .collect()
 }
 
-fn create_id(id: i32) -> String {
-    format!("ID_{}", id)
+// the xmlns declarations made on an element, the mappings in scope there
+// that its parent does not have
+pub fn declared_namespaces(namespace: &Namespace, parent: Option<&Namespace>) -> Vec<(String, String)> {
+    namespace
+        .0
+        .iter()
+        .filter(|(prefix, uri)| {
+            let predefined = matches!(prefix.as_str(), "xml" | "xmlns") || (prefix.is_empty() && uri.is_empty());
+            !predefined && parent.and_then(|p| p.get(prefix.as_str())) != Some(uri.as_str())
+        })
+        .map(|(prefix, uri)| (prefix.to_string(), uri.to_string()))
+        .collect()
 }
 
-pub fn read_xaml(filename: &String) -> Result<UIElementRef, std::io::Error> {
-    println!("Parsing: {}", filename);
+// converts an error of the xml-rs parser
+pub fn syntax_error(source_name: &str, e: &xml::reader::Error) -> XamlError {
+    let pos = SourcePos::new(source_name, e.position());
+    let message = match e.kind() {
+        xml::reader::ErrorKind::Syntax(msg) => {
+            if let Some(prefix) = unbound_prefix_from_message(msg) {
+                return XamlError::UnboundPrefix { pos, prefix };
+            }
+            msg.to_string()
+        }
+        xml::reader::ErrorKind::Io(err) => err.to_string(),
+        xml::reader::ErrorKind::Utf8(err) => err.to_string(),
+        xml::reader::ErrorKind::UnexpectedEof => "unexpected end of file".to_string(),
+    };
+    XamlError::Syntax { pos, message }
+}
 
-    let file = File::open(filename)?;
-    let file_reader = BufReader::new(file); // Buffering is important for performance
+fn create_id(id: i32) -> String {
+    format!("ID_{}", id)
+}
 
-    let parser = EventReader::new(file_reader);
-    let mut depth = 0;
+pub struct XamlReader {
+    strict: bool,
+    registry: ElementRegistry,
+}
 
-    let mut parse_stack: Vec<UIElementRef> = Vec::new();
+impl XamlReader {
+    pub fn new() -> XamlReader {
+        XamlReader::with_registry(ElementRegistry::with_builtins())
+    }
 
-    let mut id = 0;
+    pub fn with_registry(registry: ElementRegistry) -> XamlReader {
+        XamlReader {
+            strict: false,
+            registry,
+        }
+    }
 
-    for e in parser {
-        match e {
-            Ok(XmlEvent::StartElement {
-                name,
-                attributes,
-                namespace: _,
-            }) => {
-                println!("{:spaces$}+{name}", "", spaces = depth * 2);
-                depth += 1;
+    // in strict mode unknown element types are reported as errors instead
+    // of being loaded as 'Unknown' placeholders
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
 
-                let new_elt = create_ui_element(&name.local_name, attributes, create_id(id));
-                id += 1;
-                
-                let last = parse_stack.last();
-                if let Some(l) = last {
-                    let mut k = l.lock();
-                    k.add_child(new_elt.clone(), l.clone());
-                }
-                parse_stack.push(new_elt);
+    // replaces '{Binding ...}' values by their fallback value, or drops them
+    // when there is none, and unescapes '{}' literals
+    fn take_bindings(
+        &self,
+        tag: &str,
+        raw_attributes: &mut Vec<xml::attribute::OwnedAttribute>,
+        pos: &SourcePos,
+    ) -> Result<Vec<(String, Binding)>, XamlError> {
+        let mut bindings = Vec::new();
+        let mut i = 0;
+        while i < raw_attributes.len() {
+            let a = &mut raw_attributes[i];
+            if !is_markup_extension(&a.value) {
+                a.value = unescape_literal(&a.value).to_string();
+                i += 1;
+                continue;
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                depth -= 1;
-                println!("{:spaces$}-{name}", "", spaces = depth * 2);
 
-                if parse_stack.len() > 1 {
-                    parse_stack.pop();
+            let key = attribute_key(&a.name);
+            let binding = MarkupExtension::parse(&a.value).and_then(|ext| match ext.name.as_str() {
+                "Binding" => Binding::from_extension(&ext),
+                n => Err(format!("unsupported markup extension {{{}}}", n)),
+            });
+            let binding = match binding {
+                Ok(b) => b,
+                Err(reason) => {
+                    return Err(XamlError::InvalidAttribute {
+                        pos: pos.clone(),
+                        property: format!("{}.{}", tag, key),
+                        value: a.value.to_string(),
+                        reason,
+                    })
                 }
-            }
-            Ok(XmlEvent::Characters(s)) => {
-                let last = parse_stack.last();
-                if let Some(l) = last {
-                    let mut k = l.lock();
-                    k.add_content_string(s);
+            };
+            trace!(target: PARSER, "{} bound to {}", key, binding);
+            match &binding.fallback {
+                Some(v) => {
+                    a.value = v.to_string();
+                    i += 1;
+                }
+                None => {
+                    raw_attributes.remove(i);
                 }
             }
-            Err(e) => {
-                eprintln!("Error: {e}");
-                break;
+            bindings.push((key, binding));
+        }
+        Ok(bindings)
+    }
+
+    fn create_ui_element(
+        &self,
+        name: &OwnedName,
+        raw_attributes: Vec<xml::attribute::OwnedAttribute>,
+        id: String,
+        pos: &SourcePos,
+    ) -> Result<UIElementRef, XamlError> {
+        let tag = name.local_name.as_str();
+
+        // elements of the language namespace (x:Array, x:String, ..) are
+        // not UI elements
+        let registered = match XamlNamespace::resolve(name.namespace.as_deref()) {
+            XamlNamespace::Language => None,
+            ns => self.registry.lookup(ns.registry_key(), tag),
+        };
+        let defs = registered.map(|r| r.properties).unwrap_or(&[]);
+
+        let mut attributes = HashMap::<String, String>::new();
+        for x in raw_attributes {
+            let key = attribute_key(&x.name);
+            if let Err(reason) = parse_property(defs, &key, &x.value) {
+                return Err(XamlError::InvalidAttribute {
+                    pos: pos.clone(),
+                    property: format!("{}.{}", tag, key),
+                    value: x.value,
+                    reason,
+                });
             }
-            // There's more: https://docs.rs/xml-rs/latest/xml/reader/enum.XmlEvent.html
-            _ => {}
+            attributes.insert(key, x.value);
+        }
+
+        match registered {
+            Some(r) => Ok((r.factory)(&attributes, id)),
+            None if self.strict => Err(XamlError::UnknownElement {
+                pos: pos.clone(),
+                element: qualified_name(name),
+            }),
+            None => Ok(static_leaker::<Unknown>(&attributes, id)),
         }
     }
 
-    assert!(parse_stack.len() == 1);
+    pub fn read_file(&self, filename: &String) -> Result<UITree, XamlError> {
+        let file = match File::open(filename) {
+            Ok(f) => f,
+            Err(error) => {
+                return Err(XamlError::Io {
+                    file: filename.to_string(),
+                    error,
+                })
+            }
+        };
+        let file_reader = BufReader::new(file); // Buffering is important for performance
+        self.read_from(filename, file_reader)
+    }
 
-    if let Some(last) = parse_stack.last()
-    {
-        return Result::Ok( last.clone() );
+    #[cfg(test)]
+    pub fn read_str(&self, source_name: &str, text: &str) -> Result<UITree, XamlError> {
+        self.read_from(source_name, text.as_bytes())
+    }
+
+    // 'source_name' is only used to report error positions, it can be a
+    // file name, a resource name or anything else that identifies the input,
+    // embedded data is read as the '&[u8]' include_bytes! gives
+    pub fn read_from<R: Read>(&self, source_name: &str, reader: R) -> Result<UITree, XamlError> {
+        debug!(target: PARSER, "parsing {}", source_name);
+
+        let mut parser = EventReader::new(reader);
+        let mut depth = 0;
+
+        let mut parse_stack: Vec<UIElementRef> = Vec::new();
+        let mut namespace_stack: Vec<Namespace> = Vec::new();
+        let mut tree = UITree::new();
+        let mut name_positions: HashMap<String, SourcePos> = HashMap::new();
+
+        let mut id = 0;
+
+        loop {
+            let e = parser.next();
+            let pos = SourcePos::new(source_name, parser.position());
+            match e {
+                Ok(XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                }) => {
+                    trace!(target: PARSER, "{:spaces$}+{name}", "", spaces = depth * 2);
+                    depth += 1;
+
+                    let mut attributes = attributes;
+                    let keys = attributes.iter().map(|a| attribute_key(&a.name)).collect();
+                    let bindings = self.take_bindings(&name.local_name, &mut attributes, &pos)?;
+                    let elt_name = get_element_name(&attributes_by_key(&attributes));
+                    let markup = XamlMarkup {
+                        tag: qualified_name(&name),
+                        namespaces: declared_namespaces(&namespace, namespace_stack.last()),
+                        attributes: keys,
+                        content_property: None,
+                        bindings,
+                    };
+                    let new_elt = self.create_ui_element(
+                        &name,
+                        attributes,
+                        create_id(id),
+                        &pos,
+                    )?;
+                    id += 1;
+                    new_elt.lock().set_markup(markup);
+
+                    let added = match parse_stack.last() {
+                        Some(parent) => tree.add_child(parent, new_elt.clone()),
+                        None => {
+                            tree.root = Some(new_elt.clone());
+                            tree.add_to_index(new_elt.clone())
+                        }
+                    };
+                    if !added {
+                        let n = elt_name.unwrap_or_default();
+                        return Err(XamlError::DuplicateName {
+                            first: name_positions[&n].clone(),
+                            pos,
+                            name: n,
+                        });
+                    }
+                    if let Some(n) = elt_name {
+                        name_positions.insert(n, pos);
+                    }
+
+                    parse_stack.push(new_elt);
+                    namespace_stack.push(namespace);
+                }
+                Ok(XmlEvent::EndElement { name }) => {
+                    namespace_stack.pop();
+                    if parse_stack.pop().is_none() {
+                        return Err(XamlError::Unbalanced {
+                            pos,
+                            element: name.local_name,
+                        });
+                    }
+                    depth -= 1;
+                    trace!(target: PARSER, "{:spaces$}-{name}", "", spaces = depth * 2);
+                }
+                Ok(XmlEvent::Characters(s)) => {
+                    let last = parse_stack.last();
+                    if let Some(l) = last {
+                        let mut k = l.lock();
+                        k.add_content_string(s);
+                    }
+                }
+                Ok(XmlEvent::EndDocument) => {
+                    break;
+                }
+                Err(e) => {
+                    return Err(syntax_error(source_name, &e));
+                }
+                // There's more: https://docs.rs/xml-rs/latest/xml/reader/enum.XmlEvent.html
+                _ => {}
+            }
+        }
+
+        let pos = SourcePos::new(source_name, parser.position());
+        if let Some(open) = parse_stack.last() {
+            return Err(XamlError::Unbalanced {
+                pos,
+                element: open.lock().get_ui_type_name().to_string(),
+            });
+        }
+
+        match tree.root {
+            Some(_) => Ok(tree),
+            None => Err(XamlError::Syntax {
+                pos,
+                message: "no root element".to_string(),
+            }),
+        }
     }
-    Result::Err(std::io::Error::new(std::io::ErrorKind::NotFound, "unknown parse stack problem"))
 }
 
+// shortcuts for the tests, the command line sets up its own XamlReader
+#[cfg(test)]
+pub fn read_xaml(filename: &String) -> Result<UITree, XamlError> {
+    XamlReader::new().read_file(filename)
+}
 
+#[cfg(test)]
+pub fn read_xaml_str(source_name: &str, text: &str) -> Result<UITree, XamlError> {
+    XamlReader::new().read_str(source_name, text)
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::xaml_reader;
+    use std::sync::Arc;
+
+    use crate::converters::{HorizontalAlignment, VerticalAlignment};
+    use crate::element_registry::{static_leaker, ElementRegistry};
+    use crate::label::Label;
+    use crate::ui_elements::{LayoutProps, UIAlloc};
+    use crate::xaml_error::XamlError;
+    use crate::xaml_reader::{self, XamlReader};
 
     #[test]
     fn it_works() {
-        let tree = xaml_reader::read_xaml(&"tests/labeltest.xaml".to_string());        
+        let tree = xaml_reader::read_xaml(&"tests/labeltest.xaml".to_string());
         if let Result::Ok(t) = tree {
-            println!("TREE ---> ");
-            let tree = t.lock();
+            let root = t.root.unwrap();
+            let tree = root.lock();
             assert!(tree.get_ui_type_name() == "Window");
-            tree.dump(0)
-            
+            assert_eq!(tree.get_children().len(), 1);
         } else {
-            println!("no tree returned from xaml parse?");
-            assert!(false);
+            panic!("no tree returned from xaml parse?");
         }
     }
+
+    #[test]
+    fn reads_from_strings_and_bytes() {
+        let text = "<Window Title=\"Hello\"><Label>World</Label></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        assert_eq!(t.root.unwrap().lock().get_ui_type_name(), "Window");
+
+        let bytes: &[u8] = include_bytes!("../tests/button-test.xaml");
+        let t = XamlReader::new().read_from("button-test", bytes).unwrap();
+        assert_eq!(t.root.unwrap().lock().get_ui_type_name(), "Window");
+
+        match xaml_reader::read_xaml_str("inline", "<Window>\n  <Label>\n</Window>") {
+            Err(XamlError::Syntax { pos, .. }) => {
+                assert_eq!(pos.file, "inline");
+                assert_eq!(pos.line, 3);
+            }
+            _ => panic!("expected a syntax error"),
+        }
+    }
+
+    #[test]
+    fn registered_elements_are_created() {
+        let text = "<Window xmlns:my=\"clr-namespace:MyApp\"><my:Gauge/><Gauge/></Window>";
+
+        let mut reader = XamlReader::new();
+        reader.set_strict(true);
+        assert!(reader.read_str("inline", text).is_err());
+
+        let mut registry = ElementRegistry::with_builtins();
+        registry.register_factory(Some("MyApp"), "Gauge", static_leaker::<Label>, Label::properties());
+        let mut reader = XamlReader::with_registry(registry);
+        reader.set_strict(true);
+        let column = text.find("<Gauge").unwrap() as u32 + 1;
+        let t = reader.read_str("inline", text);
+        assert!(matches!(t, Err(XamlError::UnknownElement { ref pos, .. }) if pos.column == column));
+
+        let mut registry = ElementRegistry::with_builtins();
+        registry.register_factory(Some("MyApp"), "Gauge", static_leaker::<Label>, Label::properties());
+        registry.register::<Label>("Gauge");
+        let mut reader = XamlReader::with_registry(registry);
+        reader.set_strict(true);
+        let t = reader.read_str("inline", text).unwrap();
+        assert_eq!(t.root.unwrap().lock().get_ui_type_name(), "Window");
+    }
+
+    #[test]
+    fn namespaces_select_element_sets() {
+        let mut registry = ElementRegistry::with_builtins();
+        registry.register_factory(Some("MyApp.Controls"), "Button", static_leaker::<Label>, Label::properties());
+        let mut reader = XamlReader::with_registry(registry);
+        reader.set_strict(true);
+
+        // the prefixed Button is the application's, not the built-in one
+        let text = "<Window xmlns=\"http://schemas.microsoft.com/winfx/2006/xaml/presentation\" \
+                    xmlns:x=\"http://schemas.microsoft.com/winfx/2006/xaml\" \
+                    xmlns:local=\"using:MyApp.Controls\" x:Class=\"MyApp.Main\">\
+                    <local:Button Text=\"mine\"/><Button Content=\"builtin\"/></Window>";
+        let t = reader.read_str("inline", text).unwrap();
+        let root = t.root.unwrap();
+        let w = root.lock();
+        assert_eq!(w.get_value("x:Class").unwrap().to_string(), "MyApp.Main");
+        assert_eq!(w.find_by_id("ID_1".to_string()).unwrap().lock().get_ui_type_name(), "Label");
+        assert_eq!(w.find_by_id("ID_2".to_string()).unwrap().lock().get_ui_type_name(), "Button");
+
+        let text = "<Window xmlns:x=\"http://schemas.microsoft.com/winfx/2006/xaml\"><x:String/></Window>";
+        assert!(matches!(reader.read_str("inline", text), Err(XamlError::UnknownElement { .. })));
+
+        let text = "<Window>\n  <foo:Gauge/>\n</Window>";
+        match reader.read_str("inline", text) {
+            Err(XamlError::UnboundPrefix { pos, prefix }) => {
+                assert_eq!(prefix, "foo");
+                assert_eq!(pos.line, 2);
+            }
+            _ => panic!("expected an unbound prefix error"),
+        }
+
+        let text = "<Window xmlns:a=\"urn:a\"><StackPanel>\n<Label a:Tag=\"x\" b:Tag=\"1\"/></StackPanel></Window>";
+        match reader.read_str("inline", text) {
+            Err(XamlError::UnboundPrefix { prefix, .. }) => assert_eq!(prefix, "b"),
+            _ => panic!("expected an unbound prefix error"),
+        }
+    }
+
+    #[test]
+    fn names_are_registered_in_the_tree() {
+        let text = "<Window xmlns:x=\"http://schemas.microsoft.com/winfx/2006/xaml\">\
+                    <StackPanel Name=\"Panel\"><Button x:Name=\"Ok\"/></StackPanel></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let ok = t.find_by_name("Ok").unwrap();
+        assert_eq!(ok.lock().get_ui_type_name(), "Button");
+        assert_eq!(ok.lock().get_name().unwrap(), "Ok");
+        assert_eq!(t.find_by_name("Panel").unwrap().lock().get_ui_type_name(), "StackLayout");
+        assert!(t.find_by_name("Cancel").is_none());
+
+        let text = "<Window xmlns:x=\"http://schemas.microsoft.com/winfx/2006/xaml\">\n\
+                    <Button x:Name=\"Ok\"/>\n<Button Name=\"Ok\"/></Window>";
+        match xaml_reader::read_xaml_str("inline", text) {
+            Err(XamlError::DuplicateName { pos, name, first }) => {
+                assert_eq!(name, "Ok");
+                assert_eq!((first.line, pos.line), (2, 3));
+            }
+            _ => panic!("expected a duplicate name error"),
+        }
+    }
+
+    #[test]
+    fn nested_elements_are_indexed() {
+        let text = "<Window><StackPanel><Grid><Button Name=\"Deep\"/></Grid></StackPanel></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let deep = t.find_by_id("ID_3".to_string()).unwrap();
+        assert_eq!(deep.lock().get_ui_type_name(), "Button");
+
+        let root = t.root.clone().unwrap();
+        assert!(root.lock().find_by_id("ID_3".to_string()).is_some());
+
+        let grid = t.find_by_id("ID_2".to_string()).unwrap();
+        let stack = t.find_by_id("ID_1".to_string()).unwrap();
+        assert!(Arc::ptr_eq(&grid.lock().get_parent().unwrap(), &stack));
+
+        // already in the tree, nothing changes
+        assert!(!t.add_child(&root, grid));
+        assert_eq!(root.lock().get_children().len(), 1);
+    }
+
+    #[test]
+    fn syntax_error_has_position() {
+        let err = xaml_reader::read_xaml(&"tests/malformed-test.xaml".to_string()).err().unwrap();
+        match err {
+            XamlError::Syntax { pos, .. } => {
+                assert_eq!(pos.file, "tests/malformed-test.xaml");
+                assert_eq!(pos.line, 8);
+            }
+            _ => panic!("expected a syntax error, got: {}", err),
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_unknown_elements() {
+        let filename = "tests/unknown-element-test.xaml".to_string();
+        assert!(xaml_reader::read_xaml(&filename).is_ok());
+
+        let mut reader = XamlReader::new();
+        reader.set_strict(true);
+        match reader.read_file(&filename) {
+            Err(XamlError::UnknownElement { pos, element }) => {
+                assert_eq!(element, "Slider");
+                assert_eq!((pos.line, pos.column), (5, 5));
+            }
+            _ => panic!("expected an unknown element error"),
+        }
+    }
+
+    #[test]
+    fn invalid_attribute_is_reported() {
+        let err = xaml_reader::read_xaml(&"tests/bad-attribute-test.xaml".to_string()).err().unwrap();
+        match err {
+            XamlError::InvalidAttribute { pos, property, value, .. } => {
+                assert_eq!(property, "TextBlock.FontSize");
+                assert_eq!(value, "huge");
+                assert_eq!(pos.line, 5);
+            }
+            _ => panic!("expected an invalid attribute error, got: {}", err),
+        }
+    }
+
+    #[test]
+    fn layout_properties_are_typed() {
+        let text = "<Window><TextBlock Name=\"T\" Margin=\"4,2\" VerticalAlignment=\"Top\" \
+                    MinWidth=\"50\" Width=\"20\"/></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let tb = t.find_by_name("T").unwrap();
+        let layout = LayoutProps::from_element(&*tb.lock());
+        assert_eq!(layout.margin.left, 4.0);
+        assert_eq!(layout.margin.bottom, 2.0);
+        assert_eq!(layout.vertical_alignment, VerticalAlignment::Top);
+        assert_eq!(layout.horizontal_alignment, HorizontalAlignment::Stretch);
+        assert_eq!(layout.requested_width(), Some(50.0));
+        assert_eq!(layout.requested_height(), None);
+
+        let err = xaml_reader::read_xaml_str("inline", "<Window Margin=\"1,2,3\"/>").err().unwrap();
+        assert!(err.to_string().contains("Window.Margin"));
+    }
 }
@@ -0,0 +1,287 @@
+use std::fs;
+
+use crate::{
+    markup_extension::escape_literal,
+    namespaces::XAML_NS,
+    ui_elements::{UIElementRef, UITree},
+};
+
+// writes trees back out as XAML, elements read from a document keep their
+// tag, xmlns declarations, attribute order and text content
+
+pub fn escape_attribute(s: &str) -> String {
+    escape_text(s).replace('"', "&quot;")
+}
+
+pub fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// the layout attributes in the order the formatter puts them
+pub const LAYOUT_ATTRIBUTES: [&str; 14] = [
+    "Grid.Row",
+    "Grid.Column",
+    "Grid.RowSpan",
+    "Grid.ColumnSpan",
+    "Width",
+    "Height",
+    "MinWidth",
+    "MinHeight",
+    "MaxWidth",
+    "MaxHeight",
+    "Margin",
+    "Padding",
+    "HorizontalAlignment",
+    "VerticalAlignment",
+];
+
+// sorting on the rank gives the canonical attribute order: xmlns
+// declarations, the name, other language directives, layout, the rest.
+// 'key' spells language directives 'x:...' whatever their prefix
+pub fn attribute_rank(key: &str) -> (usize, usize) {
+    if key == "xmlns" || key.starts_with("xmlns:") {
+        return (0, 0);
+    }
+    if key == "x:Name" || key == "Name" {
+        return (1, 0);
+    }
+    if key.starts_with("x:") {
+        return (2, 0);
+    }
+    match LAYOUT_ATTRIBUTES.iter().position(|a| *a == key) {
+        Some(i) => (3, i),
+        None => (4, 0),
+    }
+}
+
+// '<tag a="1" b="2"' followed by 'close', with one attribute per line when
+// the single line would be wider than 'max_width'
+pub fn start_tag(
+    pad: &str,
+    indent: &str,
+    tag: &str,
+    attributes: &[(String, String)],
+    close: &str,
+    max_width: Option<usize>,
+) -> String {
+    let attributes: Vec<String> = attributes
+        .iter()
+        .map(|(n, v)| format!("{}=\"{}\"", n, escape_attribute(v)))
+        .collect();
+    let line = format!("{}<{}{}{}{}", pad, tag, if attributes.is_empty() { "" } else { " " }, attributes.join(" "), close);
+    let too_wide = max_width.map(|w| line.chars().count() > w).unwrap_or(false);
+    if !too_wide || attributes.len() < 2 {
+        return line;
+    }
+    let mut ret = format!("{}<{}", pad, tag);
+    for a in attributes.iter() {
+        ret.push_str(&format!("\n{}{}{}", pad, indent, a));
+    }
+    ret.push_str(close);
+    ret
+}
+
+// the tag of an element that was created in code
+fn default_tag(type_name: &str) -> String {
+    match type_name {
+        "StackLayout" => "StackPanel".to_string(),
+        "UnknownElementType" => "Unknown".to_string(),
+        t => t.replace('_', "."),
+    }
+}
+
+pub struct XamlWriter {
+    indent: String,
+    max_width: Option<usize>,
+}
+
+impl XamlWriter {
+    pub fn new() -> XamlWriter {
+        XamlWriter {
+            indent: "    ".to_string(),
+            max_width: None,
+        }
+    }
+
+    pub fn write_tree(&self, tree: &UITree) -> Result<String, String> {
+        match &tree.root {
+            Some(root) => Ok(self.write_element(root)),
+            None => Err("the document has no root element".to_string()),
+        }
+    }
+
+    pub fn write_element(&self, elt: &UIElementRef) -> String {
+        let mut out = String::new();
+        let mut scope = Vec::new();
+        self.write(elt, 0, &mut scope, &mut out);
+        out
+    }
+
+    // 'scope' holds the (prefix, uri) mappings declared by the ancestors
+    fn write(&self, elt: &UIElementRef, depth: usize, scope: &mut Vec<(String, String)>, out: &mut String) {
+        let k = elt.lock();
+        let markup = k.get_markup();
+        let scope_len = scope.len();
+        let tag = if markup.tag.is_empty() {
+            default_tag(k.get_ui_type_name())
+        } else {
+            markup.tag.to_string()
+        };
+
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        for (prefix, uri) in markup.namespaces.iter() {
+            let key = if prefix.is_empty() {
+                "xmlns".to_string()
+            } else {
+                format!("xmlns:{}", prefix)
+            };
+            attributes.push((key, uri.to_string()));
+            scope.push((prefix.to_string(), uri.to_string()));
+        }
+
+        // bound properties are written as their binding, not their value
+        let binding = |key: &str| markup.bindings.iter().find(|(n, _)| n == key).map(|(_, b)| b.to_string());
+        let mut values: Vec<(String, String)> = k
+            .get_values()
+            .into_iter()
+            .filter(|(n, _)| binding(n).is_none())
+            .map(|(n, v)| (n, v.to_string()))
+            .collect();
+
+        let mut text = None;
+        if let Some(content) = &markup.content_property {
+            if let Some(i) = values.iter().position(|(n, _)| n == content) {
+                text = Some(values.remove(i).1);
+            }
+        }
+        for (_, v) in values.iter_mut() {
+            *v = escape_literal(v);
+        }
+
+        // set values in document order first, then the ones set later
+        let mut ordered = Vec::new();
+        for key in markup.attributes.iter() {
+            if let Some(b) = binding(key) {
+                ordered.push((key.to_string(), b));
+            } else if let Some(i) = values.iter().position(|(n, _)| n == key) {
+                ordered.push(values.remove(i));
+            }
+        }
+        ordered.extend(values);
+        for (key, b) in markup.bindings.iter() {
+            if !markup.attributes.contains(key) {
+                ordered.push((key.to_string(), b.to_string()));
+            }
+        }
+
+        // the reader stores language directives as 'x:...' whatever prefix
+        // the document used
+        if ordered.iter().any(|(n, _)| n.starts_with("x:")) {
+            let bound = scope.iter().rev().find(|(_, uri)| uri == XAML_NS).map(|(p, _)| p.to_string());
+            let prefix = match bound {
+                Some(p) => p,
+                None => {
+                    attributes.push(("xmlns:x".to_string(), XAML_NS.to_string()));
+                    scope.push(("x".to_string(), XAML_NS.to_string()));
+                    "x".to_string()
+                }
+            };
+            for (n, _) in ordered.iter_mut() {
+                if let Some(local) = n.strip_prefix("x:") {
+                    *n = format!("{}:{}", prefix, local);
+                }
+            }
+        }
+        attributes.extend(ordered);
+
+        let pad = self.indent.repeat(depth);
+        let children = k.get_children();
+        match (children.is_empty(), text) {
+            (true, None) => {
+                out.push_str(&start_tag(&pad, &self.indent, &tag, &attributes, " />", self.max_width));
+                out.push('\n');
+            }
+            (true, Some(t)) => {
+                out.push_str(&start_tag(&pad, &self.indent, &tag, &attributes, ">", self.max_width));
+                out.push_str(&format!("{}</{}>\n", escape_text(&t), tag));
+            }
+            (false, text) => {
+                out.push_str(&start_tag(&pad, &self.indent, &tag, &attributes, ">", self.max_width));
+                out.push('\n');
+                if let Some(t) = text {
+                    out.push_str(&format!("{}{}{}\n", pad, self.indent, escape_text(&t)));
+                }
+                for c in children.iter() {
+                    self.write(c, depth + 1, scope, out);
+                }
+                out.push_str(&format!("{}</{}>\n", pad, tag));
+            }
+        }
+        scope.truncate(scope_len);
+    }
+}
+
+pub fn write_xaml(tree: &UITree) -> Result<String, String> {
+    XamlWriter::new().write_tree(tree)
+}
+
+pub fn save_xaml(tree: &UITree, path: &str) -> Result<(), String> {
+    let text = write_xaml(tree)?;
+    fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::properties::PropertyValue;
+    use crate::xaml_reader;
+    use crate::xaml_writer::write_xaml;
+
+    #[test]
+    fn round_trips_documents() {
+        let t = xaml_reader::read_xaml(&"tests/grid-test.xaml".to_string()).unwrap();
+        let text = write_xaml(&t).unwrap();
+        assert!(text.starts_with("<Window xmlns=\"http://schemas.microsoft.com/winfx/2006/xaml/presentation\" \
+                                  xmlns:x=\"http://schemas.microsoft.com/winfx/2006/xaml\" x:Class=\"GridSample.Window1\" \
+                                  Width=\"450\" Height=\"300\" Title=\"MyWindowName\""));
+        assert!(text.contains("\n    <Grid Name=\"MCGrid\""));
+        assert!(text.contains("\n        <Grid.ColumnDefinitions>\n            <ColumnDefinition />\n"));
+        assert!(text.contains("<TextBlock Grid.Row=\"1\" Grid.Column=\"0\" FontSize=\"12\">Mahesh Chand</TextBlock>"));
+        let again = xaml_reader::read_xaml_str("written", &text).unwrap();
+        assert_eq!(write_xaml(&again).unwrap(), text);
+    }
+
+    #[test]
+    fn writes_changes_and_prefixes() {
+        let text = "<Window xmlns:w=\"http://schemas.microsoft.com/winfx/2006/xaml\" xmlns:my=\"using:MyApp\">\
+                    <my:Gauge w:Name=\"g\" Value=\"1 &lt; 2\"/><Button>Ok</Button></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let button = t.root.as_ref().unwrap().lock().get_children()[1].clone();
+        button.lock().set_value("Width", PropertyValue::Float(80.0));
+        assert_eq!(
+            write_xaml(&t).unwrap(),
+            "<Window xmlns:my=\"using:MyApp\" xmlns:w=\"http://schemas.microsoft.com/winfx/2006/xaml\">\n\
+             \x20   <my:Gauge w:Name=\"g\" Value=\"1 &lt; 2\" />\n\
+             \x20   <Button Width=\"80\">Ok</Button>\n\
+             </Window>\n"
+        );
+    }
+
+    #[test]
+    fn writes_bindings() {
+        let text = "<Window><TextBlock Width=\"{Binding W, Mode=OneTime}\" Text=\"{Binding Path=Name, FallbackValue='a, b'}\" \
+                    ToolTip=\"{}{literal}\"/></Window>";
+        let t = xaml_reader::read_xaml_str("inline", text).unwrap();
+        let tb = t.find_by_id("ID_1".to_string()).unwrap();
+        assert_eq!(tb.lock().get_value("Text").unwrap().to_string(), "a, b");
+        assert_eq!(tb.lock().get_value("ToolTip").unwrap().to_string(), "{literal}");
+        tb.lock().set_value("Text", PropertyValue::Str("changed".to_string()));
+        assert_eq!(
+            write_xaml(&t).unwrap(),
+            "<Window>\n\
+             \x20   <TextBlock Width=\"{Binding W, Mode=OneTime}\" Text=\"{Binding Name, FallbackValue='a, b'}\" ToolTip=\"{}{literal}\" />\n\
+             </Window>\n"
+        );
+    }
+}